@@ -1,4 +1,6 @@
 use super::errors::FxError;
+use super::layout::Column;
+use super::matcher::SearchMode;
 use super::state::FELIX;
 
 use serde::Deserialize;
@@ -21,6 +23,84 @@ pub const CONFIG_EXAMPLE: &str = "# (Optional)
 #   zathura:
 #     [pdf]
 
+# (Optional)
+# Key sequence: action name
+# Overrides the default key bindings. Multi-key sequences such as `gg`,
+# and modifiers such as `<C-r>` (Ctrl), `<A-j>` (Alt) are available.
+# Bind `none` to disable the default key sequence.
+# A sequence replaces the default ones it overlaps with (e.g. `g` replaces
+# `gg`, `gt` and `gT`), while overlapping sequences in the config are an error.
+# See the help (`:h`) for the active key bindings.
+# keymap:
+#   x: delete
+#   dd: none
+#   <C-d>: go_bottom
+
+# (Optional)
+# Key bindings in the select mode, in the same way as keymap.
+# Keys not bound here fall back to the movement keys of the normal mode.
+# Available actions: delete, yank, cut, bulk_rename, rename (by the pattern),
+# edit_permissions, shell, select (return to the normal mode), and the movement.
+# select_keymap:
+#   D: delete
+#   d: none
+
+# (Optional)
+# Named commands invoked by `:<name>` in the shell mode, or bound to keys
+# by `:<name>` in the keymap section (e.g. `ga: \":git-add\"`).
+# The placeholders (%s, %f, %d) are expanded as in the shell mode, and
+# the rest of the line is appended to the command.
+# Options:
+#   wait: wait for Enter after the command exits, to read the output.
+#   reload: reload the directory afterwards. (default: true)
+#   background: run the command as a job, without leaving felix.
+# custom_commands:
+#   git-add: \"git add %s\"
+#   compress:
+#     cmd: \"tar czf %d.tgz %s\"
+#     background: true
+#   git-log:
+#     cmd: \"git log --oneline -20\"
+#     wait: true
+#     reload: false
+
+# (Optional)
+# Default mode of the search (`/`). Tab in the search mode switches it.
+#   SmartCase: substring of the name.
+#   Regex: regular expression such as `\\.rs$`.
+#   Glob: glob such as `*.rs`, matched against the whole name.
+# In every mode, the search is case-insensitive unless the keyword
+# contains an upper case letter.
+# If not set, will default to SmartCase.
+# search_mode: SmartCase
+
+# (Optional)
+# Whether to use the XDG trash ($XDG_DATA_HOME/Trash, and .Trash-$UID of
+# other mount points) instead of felix's own trash directory, so that
+# deleted items are shared with the desktop trash. (Linux and other Unix only)
+# If not set, will default to false.
+# xdg_trash: true
+
+# (Optional)
+# Whether to execute the command line of the shell mode through `$SHELL -c`,
+# so that pipes, globs, quotes and redirections are available.
+# The placeholders (%s, %f, %d) are quoted for the shell.
+# If not set, will default to false.
+# use_shell: true
+
+# (Optional)
+# Columns shown after the name in the item list, from left to right.
+# Pick from the following:
+#    Size
+#    Mtime          // modified time
+#    Permissions    // such as drwxr-xr-x
+#    Owner          // user and group
+#    LinkTarget     // target of the symlink
+#    Count          // number of items in the directory
+# Columns that do not fit in the terminal are hidden from the right.
+# If not set, will default to [Mtime].
+# columns: [Size, Permissions, Mtime]
+
 # (Optional)
 # Whether to use syntax highlighting in the preview mode.
 # If not set, will default to false.
@@ -79,6 +159,26 @@ pub struct Config {
     pub syntax_highlight: Option<bool>,
     pub default_theme: Option<DefaultTheme>,
     pub theme_path: Option<PathBuf>,
+    pub keymap: Option<BTreeMap<String, String>>,
+    pub select_keymap: Option<BTreeMap<String, String>>,
+    pub xdg_trash: Option<bool>,
+    pub use_shell: Option<bool>,
+    pub custom_commands: Option<BTreeMap<String, CustomCommand>>,
+    pub search_mode: Option<SearchMode>,
+    pub columns: Option<Vec<Column>>,
+}
+
+/// Command line, or the command line with the options.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum CustomCommand {
+    Line(String),
+    Options {
+        cmd: String,
+        wait: Option<bool>,
+        reload: Option<bool>,
+        background: Option<bool>,
+    },
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -134,6 +234,43 @@ impl Default for Config {
             syntax_highlight: Default::default(),
             default_theme: Default::default(),
             theme_path: Default::default(),
+            keymap: Default::default(),
+            select_keymap: Default::default(),
+            xdg_trash: Default::default(),
+            use_shell: Default::default(),
+            custom_commands: Default::default(),
+            search_mode: Default::default(),
+            columns: Default::default(),
+        }
+    }
+}
+
+impl CustomCommand {
+    pub fn cmd(&self) -> &str {
+        match self {
+            CustomCommand::Line(cmd) => cmd,
+            CustomCommand::Options { cmd, .. } => cmd,
+        }
+    }
+
+    pub fn wait(&self) -> bool {
+        match self {
+            CustomCommand::Line(_) => false,
+            CustomCommand::Options { wait, .. } => wait.unwrap_or(false),
+        }
+    }
+
+    pub fn reload(&self) -> bool {
+        match self {
+            CustomCommand::Line(_) => true,
+            CustomCommand::Options { reload, .. } => reload.unwrap_or(true),
+        }
+    }
+
+    pub fn background(&self) -> bool {
+        match self {
+            CustomCommand::Line(_) => false,
+            CustomCommand::Options { background, .. } => background.unwrap_or(false),
         }
     }
 }
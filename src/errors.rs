@@ -18,6 +18,12 @@ pub enum FxError {
     TooSmallWindowSize,
     Log(String),
     Unpack(String),
+    Keymap(String),
+    Rename(String),
+    Command(String),
+    Pattern(String),
+    Permissions(String),
+    Cancelled,
     Panic,
     #[cfg(any(target_os = "linux", target_os = "netbsd"))]
     Nix(String),
@@ -44,6 +50,12 @@ impl std::fmt::Display for FxError {
             FxError::TooSmallWindowSize => "Error: Too small window size".to_owned(),
             FxError::Log(s) => s.to_owned(),
             FxError::Unpack(s) => s.to_owned(),
+            FxError::Keymap(s) => s.to_owned(),
+            FxError::Rename(s) => s.to_owned(),
+            FxError::Command(s) => format!("Error: Command failed -> {}", s),
+            FxError::Pattern(s) => s.to_owned(),
+            FxError::Permissions(s) => s.to_owned(),
+            FxError::Cancelled => "Cancelled.".to_owned(),
             FxError::Panic => "Error: felix panicked".to_owned(),
             #[cfg(any(target_os = "linux", target_os = "netbsd"))]
             FxError::Nix(s) => s.to_owned(),
@@ -69,6 +81,18 @@ impl From<syntect::Error> for FxError {
     }
 }
 
+impl From<regex::Error> for FxError {
+    fn from(err: regex::Error) -> Self {
+        FxError::Pattern(err.to_string())
+    }
+}
+
+impl From<globset::Error> for FxError {
+    fn from(err: globset::Error) -> Self {
+        FxError::Pattern(err.to_string())
+    }
+}
+
 impl From<walkdir::Error> for FxError {
     fn from(err: walkdir::Error) -> Self {
         FxError::WalkDir(err.to_string())
@@ -0,0 +1,367 @@
+use super::errors::FxError;
+use super::line_editor::{truncate_to_width, LineEditor, LineInput};
+use super::state::BEGINNING_ROW;
+use super::term::*;
+
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::style::Stylize;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ignore::gitignore::Gitignore;
+use std::io::{Read, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Interval to receive the walked items while waiting for key input.
+const FINDER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// The walk stops when this number of items is found.
+const MAX_CANDIDATES: usize = 200_000;
+/// Bytes read from the file to preview.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Fuzzy finder of the items under the directory.
+/// Items are streamed from the walker thread, and ranked each time the query changes.
+struct Finder {
+    root: PathBuf,
+    receiver: Receiver<String>,
+    /// Paths relative to the root, with `/` at the end of directories.
+    candidates: Vec<String>,
+    walking: bool,
+    matcher: SkimMatcherV2,
+    query: String,
+    /// Indices of the matched candidates and their scores, sorted by rank.
+    matches: Vec<(usize, i64)>,
+    index: usize,
+}
+
+impl Finder {
+    fn new(root: &Path, show_hidden: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let walker_root = root.to_path_buf();
+        thread::spawn(move || send_candidates(&walker_root, show_hidden, sender));
+        Finder {
+            root: root.to_path_buf(),
+            receiver,
+            candidates: Vec::new(),
+            walking: true,
+            matcher: SkimMatcherV2::default().smart_case(),
+            query: String::new(),
+            matches: Vec::new(),
+            index: 0,
+        }
+    }
+
+    /// Take the items walked so far, and rank the new ones.
+    fn receive(&mut self) -> bool {
+        let start = self.candidates.len();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(candidate) => self.candidates.push(candidate),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.walking = false;
+                    break;
+                }
+            }
+        }
+        if self.candidates.len() == start {
+            return false;
+        }
+        self.rank(start);
+        true
+    }
+
+    /// Score the candidates from `start`, and sort all the matches.
+    fn rank(&mut self, start: usize) {
+        for (i, candidate) in self.candidates.iter().enumerate().skip(start) {
+            if self.query.is_empty() {
+                self.matches.push((i, 0));
+            } else if let Some(score) = self.matcher.fuzzy_match(candidate, &self.query) {
+                self.matches.push((i, score));
+            }
+        }
+        //Higher score first, then shorter path, keeping the walked order.
+        let candidates = &self.candidates;
+        self.matches.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .cmp(a_score)
+                .then(candidates[*a].len().cmp(&candidates[*b].len()))
+                .then(a.cmp(b))
+        });
+    }
+
+    fn set_query(&mut self, query: &str) {
+        self.query = query.to_owned();
+        self.matches.clear();
+        self.index = 0;
+        self.rank(0);
+    }
+
+    fn selected(&self) -> Option<&str> {
+        self.matches
+            .get(self.index)
+            .map(|(i, _)| self.candidates[*i].as_str())
+    }
+
+    fn draw(&self, editor: &LineEditor) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        let header = format!(
+            " Find in {} ({}/{}{}) Enter: jump, Esc: cancel",
+            self.root.display(),
+            self.matches.len(),
+            self.candidates.len(),
+            if self.walking { "+" } else { "" }
+        );
+        move_to(1, 1);
+        print!("{}", truncate_to_width(&header, width as usize).bold());
+
+        let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+        let list_width = (width / 2) as usize;
+        let skip = (self.index + 1).saturating_sub(rows);
+        for (row, (i, _)) in self.matches.iter().enumerate().skip(skip).take(rows) {
+            move_to(1, (row - skip) as u16 + BEGINNING_ROW);
+            let candidate = &self.candidates[*i];
+            let indices = self
+                .matcher
+                .fuzzy_indices(candidate, &self.query)
+                .map(|(_, indices)| indices)
+                .unwrap_or_default();
+            let marker = if row == self.index { "> " } else { "  " };
+            print!("{}", marker);
+            let line = truncate_to_width(candidate, list_width.saturating_sub(3));
+            for (j, c) in line.chars().enumerate() {
+                let c = if indices.contains(&j) {
+                    c.bold().yellow()
+                } else {
+                    c.stylize()
+                };
+                if row == self.index {
+                    print!("{}", c.negative());
+                } else {
+                    print!("{}", c);
+                }
+            }
+        }
+
+        //Preview of the selected item on the right half.
+        if let Some(selected) = self.selected() {
+            let preview_width = (width as usize).saturating_sub(list_width + 2);
+            for (row, line) in preview(&self.root.join(selected), rows).iter().enumerate() {
+                move_to(list_width as u16 + 2, row as u16 + BEGINNING_ROW);
+                print!("{}", truncate_to_width(line, preview_width).dark_grey());
+            }
+        }
+
+        editor.draw();
+        Ok(())
+    }
+}
+
+/// Walk the directory recursively, skipping hidden items (unless `show_hidden`) and ones ignored by `.gitignore`.
+/// The walk stops when `f` returns false.
+pub fn walk(root: &Path, show_hidden: bool, mut f: impl FnMut(&walkdir::DirEntry) -> bool) {
+    //`.gitignore` of the directories from the root to the current one, indexed by the depth.
+    let mut ignores: Vec<Gitignore> = Vec::new();
+    let walker = walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let depth = entry.depth();
+            ignores.truncate(depth);
+            let is_dir = entry.file_type().is_dir();
+            if depth > 0 {
+                let name = entry.file_name().to_string_lossy();
+                if (!show_hidden && name.starts_with('.')) || (is_dir && name == ".git") {
+                    return false;
+                }
+                for ignore in ignores.iter().rev() {
+                    let matched = ignore.matched(entry.path(), is_dir);
+                    if matched.is_ignore() {
+                        return false;
+                    } else if matched.is_whitelist() {
+                        break;
+                    }
+                }
+            }
+            if is_dir {
+                let gitignore = entry.path().join(".gitignore");
+                ignores.push(if gitignore.exists() {
+                    Gitignore::new(gitignore).0
+                } else {
+                    Gitignore::empty()
+                });
+            }
+            true
+        });
+
+    for entry in walker.flatten().filter(|e| e.depth() > 0) {
+        if !f(&entry) {
+            break;
+        }
+    }
+}
+
+/// Send the paths relative to the root, until the finder is closed.
+fn send_candidates(root: &Path, show_hidden: bool, sender: Sender<String>) {
+    let mut count = 0;
+    walk(root, show_hidden, |entry| {
+        let mut candidate = match entry.path().strip_prefix(root) {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => return true,
+        };
+        if entry.file_type().is_dir() {
+            candidate.push('/');
+        }
+        count += 1;
+        sender.send(candidate).is_ok() && count < MAX_CANDIDATES
+    });
+}
+
+/// Lines to preview: the items in the directory, or the beginning of the text file.
+fn preview(p: &Path, rows: usize) -> Vec<String> {
+    if p.is_dir() {
+        let mut names: Vec<String> = match std::fs::read_dir(p) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(e) => return vec![e.to_string()],
+        };
+        names.sort();
+        names.truncate(rows);
+        return names;
+    }
+
+    let mut buf = Vec::new();
+    if let Err(e) = std::fs::File::open(p).and_then(|f| f.take(PREVIEW_BYTES).read_to_end(&mut buf))
+    {
+        return vec![e.to_string()];
+    }
+    if content_inspector::inspect(&buf).is_binary() {
+        return vec!["(Binary file)".to_owned()];
+    }
+    String::from_utf8_lossy(&buf)
+        .lines()
+        .take(rows)
+        .map(|line| {
+            line.replace('\t', "    ")
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect()
+        })
+        .collect()
+}
+
+/// Show the fuzzy finder of the items under the directory.
+/// Returns the path of the selected item, or `None` if cancelled.
+pub fn find(
+    root: &Path,
+    show_hidden: bool,
+    mut screen: &Stdout,
+) -> Result<Option<PathBuf>, FxError> {
+    let mut finder = Finder::new(root, show_hidden);
+    let mut editor = LineEditor::new("> ", "");
+    let mut redraw = true;
+    show_cursor();
+    loop {
+        if finder.receive() {
+            redraw = true;
+        }
+        if redraw {
+            finder.draw(&editor)?;
+            screen.flush()?;
+            redraw = false;
+        }
+
+        if !crossterm::event::poll(FINDER_POLL_INTERVAL)? {
+            continue;
+        }
+        redraw = true;
+        if let Event::Key(key) = crossterm::event::read()? {
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Down | KeyCode::Tab => {
+                    if finder.index + 1 < finder.matches.len() {
+                        finder.index += 1;
+                    }
+                    continue;
+                }
+                KeyCode::Char('n') if ctrl => {
+                    if finder.index + 1 < finder.matches.len() {
+                        finder.index += 1;
+                    }
+                    continue;
+                }
+                KeyCode::Up | KeyCode::BackTab => {
+                    finder.index = finder.index.saturating_sub(1);
+                    continue;
+                }
+                KeyCode::Char('p') if ctrl => {
+                    finder.index = finder.index.saturating_sub(1);
+                    continue;
+                }
+                _ => {}
+            }
+            match editor.handle(key) {
+                LineInput::Enter => {
+                    hide_cursor();
+                    return Ok(finder
+                        .selected()
+                        .map(|selected| root.join(selected.trim_end_matches('/'))));
+                }
+                LineInput::Cancel => {
+                    hide_cursor();
+                    return Ok(None);
+                }
+                LineInput::Changed => finder.set_query(editor.text()),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_and_rank() {
+        let base = std::env::temp_dir().join("felix_test_finder");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("src/nested")).unwrap();
+        std::fs::create_dir_all(base.join("target")).unwrap();
+        std::fs::create_dir_all(base.join(".hidden")).unwrap();
+        std::fs::write(base.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(base.join("src/main.rs"), "").unwrap();
+        std::fs::write(base.join("src/nested/mod.rs"), "").unwrap();
+        std::fs::write(base.join("src/debug.log"), "").unwrap();
+        std::fs::write(base.join("target/out"), "").unwrap();
+        std::fs::write(base.join(".hidden/file"), "").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        send_candidates(&base, false, sender);
+        let walked: Vec<String> = receiver.iter().collect();
+        assert_eq!(
+            walked,
+            vec!["src/", "src/main.rs", "src/nested/", "src/nested/mod.rs"]
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        send_candidates(&base, true, sender);
+        assert!(receiver.iter().any(|p| p == ".hidden/file"));
+
+        let mut finder = Finder::new(&base, false);
+        while finder.walking {
+            finder.receive();
+        }
+        finder.set_query("main");
+        assert_eq!(finder.selected(), Some("src/main.rs"));
+        finder.set_query("zzz");
+        assert_eq!(finder.selected(), None);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,16 +1,20 @@
 use super::config::Colorname;
 use super::errors::FxError;
+use super::job::{copy_item, remove_item, Progress};
+use super::line_editor::{LineEditor, LineInput};
 use super::term::*;
 
+use crossterm::event::Event;
 use crossterm::style::Stylize;
 use log::{info, warn};
 use simplelog::{ConfigBuilder, LevelFilter, WriteLogger};
-use std::collections::{BTreeMap, BTreeSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ffi::OsString;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-pub const PROCESS_INDICATOR_LENGTH: u16 = 7;
 const KB: u64 = 1000;
 const MB: u64 = 1_000_000;
 const GB: u64 = 1_000_000_000;
@@ -70,6 +74,154 @@ pub fn rename_dir(dir_name: &str, name_set: &BTreeSet<String>) -> String {
     new_name
 }
 
+/// Move an item to the destination path.
+/// On the same filesystem the item is just renamed; across devices it is copied and then removed.
+pub fn move_item(from: &Path, to: &Path) -> Result<(), FxError> {
+    if let Err(e) = std::fs::rename(from, to) {
+        if is_same_device(from, to) {
+            return Err(e.into());
+        }
+        copy_item(from, to, &Progress::default())?;
+        if remove_item(from).is_err() {
+            return Err(FxError::RemoveItem(from.to_owned()));
+        }
+    }
+    Ok(())
+}
+
+/// Rename items at once, as pairs of the original path and the new one.
+/// Items are renamed via temporary names first, so that swaps and cycles are safe.
+/// If an error occurs on the way, the items are renamed back.
+pub fn rename_items(pairs: &[(PathBuf, PathBuf)]) -> Result<(), FxError> {
+    let temp: Vec<PathBuf> = pairs
+        .iter()
+        .enumerate()
+        .map(|(i, (from, _))| {
+            from.with_file_name(format!(".felix_rename_{}_{}", std::process::id(), i))
+        })
+        .collect();
+
+    for (i, (from, _)) in pairs.iter().enumerate() {
+        if let Err(e) = std::fs::rename(from, &temp[i]) {
+            rename_back(pairs, &temp, i, 0);
+            return Err(e.into());
+        }
+    }
+    for (i, (_, to)) in pairs.iter().enumerate() {
+        //Never overwrite the item not to be renamed.
+        let result = if to.symlink_metadata().is_ok() {
+            Err(FxError::Rename(format!("Already exists: {}", to.display())))
+        } else {
+            std::fs::rename(&temp[i], to).map_err(FxError::from)
+        };
+        if let Err(e) = result {
+            rename_back(pairs, &temp, pairs.len(), i);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Undo the first `temp_done` renames to the temporary names,
+/// and the first `final_done` renames from the temporary names to the new ones.
+fn rename_back(
+    pairs: &[(PathBuf, PathBuf)],
+    temp: &[PathBuf],
+    temp_done: usize,
+    final_done: usize,
+) {
+    for i in (0..final_done).rev() {
+        if let Err(e) = std::fs::rename(&pairs[i].1, &temp[i]) {
+            warn!("Cannot rename back {:?}: {}", pairs[i].1, e);
+        }
+    }
+    for i in (0..temp_done).rev() {
+        if let Err(e) = std::fs::rename(&temp[i], &pairs[i].0) {
+            warn!("Cannot rename back {:?}: {}", temp[i], e);
+        }
+    }
+}
+
+/// Compare the names with the edited ones (one name per line), and return the changed names with their index.
+/// `existing` is the names of all items in the directory.
+pub fn diff_names(
+    names: &[String],
+    edited: &str,
+    existing: &BTreeSet<String>,
+) -> Result<Vec<(usize, String)>, FxError> {
+    let edited: Vec<&str> = edited.lines().collect();
+    if edited.len() != names.len() {
+        return Err(FxError::Rename(format!(
+            "The number of lines changed: {} -> {}",
+            names.len(),
+            edited.len()
+        )));
+    }
+    changed_names(names, &edited, existing)
+}
+
+/// Check the new names against each other and the existing names,
+/// and return the changed names with their index.
+pub fn changed_names<T: AsRef<str>>(
+    names: &[String],
+    new_names: &[T],
+    existing: &BTreeSet<String>,
+) -> Result<Vec<(usize, String)>, FxError> {
+    let mut name_set = BTreeSet::new();
+    let mut result = Vec::new();
+    for (i, (name, new_name)) in names.iter().zip(new_names.iter()).enumerate() {
+        let new_name = new_name.as_ref();
+        if !name_set.insert(new_name) {
+            return Err(FxError::Rename(format!("Duplicate name: {}", new_name)));
+        }
+        if name == new_name {
+            continue;
+        }
+        if new_name.is_empty()
+            || new_name == "."
+            || new_name == ".."
+            || new_name.contains(std::path::is_separator)
+        {
+            return Err(FxError::Rename(format!("Invalid name: {:?}", new_name)));
+        }
+        //Names of the items to be renamed are checked as duplicates above.
+        if existing.contains(new_name) && !names.iter().any(|name| name == new_name) {
+            return Err(FxError::Rename(format!("Already exists: {}", new_name)));
+        }
+        result.push((i, new_name.to_owned()));
+    }
+    Ok(result)
+}
+
+/// Check if the item is modified later than the other one.
+pub fn is_newer(p: &Path, other: &Path) -> bool {
+    let modified = |p: &Path| std::fs::symlink_metadata(p).and_then(|m| m.modified());
+    match (modified(p), modified(other)) {
+        (Ok(time), Ok(other_time)) => time > other_time,
+        _ => false,
+    }
+}
+
+#[cfg(target_family = "unix")]
+/// Check if the item and the destination directory are on the same device.
+pub fn is_same_device(from: &Path, to: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let to_dir = match to.parent() {
+        Some(dir) => dir,
+        None => return false,
+    };
+    match (std::fs::symlink_metadata(from), std::fs::metadata(to_dir)) {
+        (Ok(from), Ok(to)) => from.dev() == to.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_family = "unix"))]
+/// Without the device information, assume that a failed rename is caused by crossing devices.
+pub fn is_same_device(_from: &Path, _to: &Path) -> bool {
+    false
+}
+
 /// Print the result of operation, such as put/delete/redo/undo.
 pub fn print_info<T: std::fmt::Display>(message: T, then: u16) {
     delete_cursor();
@@ -81,7 +233,7 @@ pub fn print_info<T: std::fmt::Display>(message: T, then: u16) {
     print!("{}", trimmed);
 
     hide_cursor();
-    move_to(1, then);
+    move_to_pointer(then);
     print_pointer();
     move_left(1);
 }
@@ -100,24 +252,16 @@ pub fn print_warning<T: std::fmt::Display>(message: T, then: u16) {
     reset_color();
 
     hide_cursor();
-    move_to(1, then);
+    move_to_pointer(then);
     print_pointer();
     move_left(1);
 }
 
-/// Print process of put/delete.
+/// Print the progress of put/delete on the info line.
 pub fn print_process<T: std::fmt::Display>(message: T) {
+    to_info_line();
+    clear_current_line();
     print!("{}", message);
-    move_left(PROCESS_INDICATOR_LENGTH);
-}
-
-/// Print the number of process (put/delete).
-pub fn display_count(i: usize, all: usize) -> String {
-    let mut result = String::new();
-    result.push_str(&(i + 1).to_string());
-    result.push('/');
-    result.push_str(&all.to_string());
-    result
 }
 
 /// Convert extension setting in the config to BTreeMap.
@@ -138,7 +282,7 @@ pub fn to_extension_map(
     Some(new_map)
 }
 
-/// Create the duration as String. Used after print_process(put/delete).
+/// Create the duration as String. Used after put/delete.
 pub fn duration_to_string(duration: Duration) -> String {
     let s = duration.as_secs_f32();
     let mut result: String = s.to_string().chars().take(4).collect();
@@ -241,9 +385,28 @@ pub fn print_help(v: &[String], skip_number: usize, row: u16) {
     }
 }
 
-/// Check if we can edit the file name safely.
-pub fn is_editable(s: &str) -> bool {
-    s.is_ascii()
+/// Read a line in the info line after the prompt. Returns `None` if cancelled by Esc.
+pub fn read_line(prompt: &str, initial: &str) -> Result<Option<String>, FxError> {
+    let mut editor = LineEditor::new(prompt, initial);
+    show_cursor();
+    loop {
+        editor.draw();
+        std::io::stdout().flush()?;
+
+        if let Event::Key(key) = crossterm::event::read()? {
+            match editor.handle(key) {
+                LineInput::Enter => {
+                    hide_cursor();
+                    return Ok(Some(editor.text().to_owned()));
+                }
+                LineInput::Cancel => {
+                    hide_cursor();
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
 /// Initialize the log if `-l` option is added.
@@ -293,11 +456,88 @@ pub fn check_version() -> Result<(), FxError> {
     Ok(())
 }
 
-/// linux-specific: Convert u32 to permission-ish string.
-pub fn convert_to_permissions(permissions: u32) -> String {
-    let permissions = format!("{permissions:o}");
-    let permissions: String = permissions.chars().rev().take(3).collect();
-    permissions.chars().rev().collect()
+/// Convert the mode to the `ls -l` style string, such as `drwxr-sr-x`.
+pub fn format_permissions(mode: u32) -> String {
+    let mut result = String::with_capacity(10);
+    result.push(match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    });
+    //setuid, setgid and sticky bits are shown in place of the execute bits.
+    for (shift, special, c) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        result.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        result.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        result.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => c,
+            (true, false) => c.to_ascii_uppercase(),
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    result
+}
+
+/// Names of the user and group, or the ids if not found.
+pub fn owner_names(uid: u32, gid: u32) -> (String, String) {
+    (user_name(uid), group_name(gid))
+}
+
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+fn user_name(uid: u32) -> String {
+    match nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid)) {
+        Ok(Some(user)) => user.name,
+        _ => uid.to_string(),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+fn group_name(gid: u32) -> String {
+    match nix::unistd::Group::from_gid(nix::unistd::Gid::from_raw(gid)) {
+        Ok(Some(group)) => group.name,
+        _ => gid.to_string(),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+fn user_name(uid: u32) -> String {
+    uid.to_string()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+fn group_name(gid: u32) -> String {
+    gid.to_string()
+}
+
+/// Cache of the user and group names by the ids, so that they are not looked up on every redraw.
+#[derive(Debug, Default)]
+pub struct OwnerNames {
+    users: RefCell<HashMap<u32, String>>,
+    groups: RefCell<HashMap<u32, String>>,
+}
+
+impl OwnerNames {
+    /// Names of the user and group, or the ids if not found.
+    pub fn get(&self, uid: u32, gid: u32) -> (String, String) {
+        let user = self
+            .users
+            .borrow_mut()
+            .entry(uid)
+            .or_insert_with(|| user_name(uid))
+            .clone();
+        let group = self
+            .groups
+            .borrow_mut()
+            .entry(gid)
+            .or_insert_with(|| group_name(gid))
+            .clone();
+        (user, group)
+    }
 }
 
 ///The length of the file name is counted by bytes(), not chars(),
@@ -320,6 +560,74 @@ pub fn split_str(s: &str, i: usize) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rename_items() {
+        let base = std::env::temp_dir().join("felix_test_rename_items");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(base.join(name), name).unwrap();
+        }
+
+        //Cycle: a -> b -> c -> a
+        let pairs = vec![
+            (base.join("a"), base.join("b")),
+            (base.join("b"), base.join("c")),
+            (base.join("c"), base.join("a")),
+        ];
+        rename_items(&pairs).unwrap();
+        assert_eq!(std::fs::read_to_string(base.join("b")).unwrap(), "a");
+        assert_eq!(std::fs::read_to_string(base.join("c")).unwrap(), "b");
+        assert_eq!(std::fs::read_to_string(base.join("a")).unwrap(), "c");
+
+        //Conflict with the item not to be renamed: everything is renamed back.
+        let pairs = vec![
+            (base.join("a"), base.join("d")),
+            (base.join("b"), base.join("c")),
+        ];
+        assert!(rename_items(&pairs).is_err());
+        assert_eq!(std::fs::read_to_string(base.join("a")).unwrap(), "c");
+        assert_eq!(std::fs::read_to_string(base.join("b")).unwrap(), "a");
+        assert!(!base.join("d").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_diff_names() {
+        let names: Vec<String> = vec!["a".to_owned(), "b".to_owned()];
+        let existing: BTreeSet<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            diff_names(&names, "b\na\n", &existing).unwrap(),
+            vec![(0, "b".to_owned()), (1, "a".to_owned())]
+        );
+        assert_eq!(
+            diff_names(&names, "a\nd\n", &existing).unwrap(),
+            vec![(1, "d".to_owned())]
+        );
+        assert!(diff_names(&names, "a\n", &existing).is_err());
+        assert!(diff_names(&names, "d\nd\n", &existing).is_err());
+        assert!(diff_names(&names, "a\nc\n", &existing).is_err());
+        assert!(diff_names(&names, "a\nx/y\n", &existing).is_err());
+    }
+
+    #[test]
+    fn test_move_item() {
+        let base = std::env::temp_dir().join("felix_test_move_item");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("from/child")).unwrap();
+        std::fs::write(base.join("from/child/a.txt"), "a").unwrap();
+
+        move_item(&base.join("from"), &base.join("to")).unwrap();
+        assert!(!base.join("from").exists());
+        assert_eq!(
+            std::fs::read_to_string(base.join("to/child/a.txt")).unwrap(),
+            "a"
+        );
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
     #[test]
     fn test_format_time() {
         let time1 = Some("1970-01-01 00:00:00".to_string());
@@ -328,11 +636,6 @@ mod tests {
         assert_eq!(format_time(&time2), "".to_string());
     }
 
-    #[test]
-    fn test_display_count() {
-        assert_eq!(display_count(1, 4), "2/4".to_string());
-    }
-
     #[test]
     fn test_proper_size() {
         assert_eq!(to_proper_size(50), "50B".to_string());
@@ -357,22 +660,12 @@ mod tests {
     }
 
     #[test]
-    fn test_is_editable() {
-        let s1 = "Hello, world!";
-        let s2 = "image.jpg";
-        let s3 = "a̐éö̲\r\n";
-        let s4 = "日本の首都は東京です";
-        assert!(is_editable(s1));
-        assert!(is_editable(s2));
-        assert!(!is_editable(s3));
-        assert!(!is_editable(s4));
-    }
-
-    #[test]
-    fn test_convert_to_permissions() {
-        let file = 33188;
-        let dir = 16877;
-        assert_eq!(&convert_to_permissions(file), "644");
-        assert_eq!(&convert_to_permissions(dir), "755");
+    fn test_format_permissions() {
+        assert_eq!(&format_permissions(0o100644), "-rw-r--r--");
+        assert_eq!(&format_permissions(0o40755), "drwxr-xr-x");
+        assert_eq!(&format_permissions(0o42755), "drwxr-sr-x");
+        assert_eq!(&format_permissions(0o41777), "drwxrwxrwt");
+        assert_eq!(&format_permissions(0o104644), "-rwSr--r--");
+        assert_eq!(&format_permissions(0o120777), "lrwxrwxrwx");
     }
 }
@@ -0,0 +1,329 @@
+use super::errors::FxError;
+use super::finder::walk;
+use super::job::JOB_POLL_INTERVAL;
+use super::layout::Layout;
+use super::line_editor::truncate_to_width;
+use super::matcher::{Matcher, SearchMode};
+use super::state::BEGINNING_ROW;
+use super::term::*;
+
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+use std::io::{Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use syntect::easy::HighlightLines;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use unicode_width::UnicodeWidthStr;
+
+/// Files larger than this are not searched.
+const MAX_SIZE_TO_SEARCH: u64 = 10 * 1024 * 1024;
+/// The search stops when this number of lines are found.
+const MAX_HITS: usize = 10_000;
+
+/// Line that matches the pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub path: PathBuf,
+    /// Line number from 1.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Results of `:grep`, received from the search thread.
+pub struct GrepView {
+    root: PathBuf,
+    pattern: String,
+    receiver: Receiver<Hit>,
+    hits: Vec<Hit>,
+    searching: bool,
+    index: usize,
+    /// Preview of the hit at the index, kept until the cursor moves.
+    preview: Option<(usize, Vec<String>)>,
+}
+
+impl GrepView {
+    /// Start searching the files under the directory in the background.
+    /// The pattern is a regex, case-insensitive unless it contains an upper case letter.
+    pub fn new(root: &Path, pattern: &str, show_hidden: bool) -> Result<Self, FxError> {
+        let matcher = Matcher::new(pattern, SearchMode::Regex)?;
+        let (sender, receiver) = mpsc::channel();
+        let search_root = root.to_path_buf();
+        thread::spawn(move || search(&search_root, &matcher, show_hidden, sender));
+        Ok(GrepView {
+            root: root.to_path_buf(),
+            pattern: pattern.to_owned(),
+            receiver,
+            hits: Vec::new(),
+            searching: true,
+            index: 0,
+            preview: None,
+        })
+    }
+
+    /// Show the results until a hit is selected by Enter, or other keys return `None`.
+    pub fn show(&mut self, layout: &Layout, mut screen: &Stdout) -> Result<Option<Hit>, FxError> {
+        let mut redraw = true;
+        loop {
+            if self.receive() {
+                redraw = true;
+            }
+            if redraw {
+                self.draw(layout)?;
+                screen.flush()?;
+                redraw = false;
+            }
+
+            if !crossterm::event::poll(JOB_POLL_INTERVAL)? {
+                continue;
+            }
+            redraw = true;
+            if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+                match code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        if self.index + 1 < self.hits.len() {
+                            self.index += 1;
+                        }
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        self.index = self.index.saturating_sub(1);
+                    }
+                    KeyCode::Char('g') => {
+                        self.index = 0;
+                    }
+                    KeyCode::Char('G') => {
+                        self.index = self.hits.len().saturating_sub(1);
+                    }
+                    KeyCode::Char('l') | KeyCode::Enter => {
+                        if let Some(hit) = self.hits.get(self.index) {
+                            return Ok(Some(hit.clone()));
+                        }
+                    }
+                    _ => {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self) -> bool {
+        let start = self.hits.len();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(hit) => self.hits.push(hit),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    if self.searching {
+                        self.searching = false;
+                        return true;
+                    }
+                    break;
+                }
+            }
+        }
+        self.hits.len() > start
+    }
+
+    fn draw(&mut self, layout: &Layout) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        let width = width as usize;
+        let header = format!(
+            " Grep /{}/ in {} ({} hit(s){}) Enter: open, other keys: return",
+            self.pattern,
+            self.root.display(),
+            self.hits.len(),
+            if self.searching { ", searching..." } else { "" }
+        );
+        move_to(1, 1);
+        print!("{}", truncate_to_width(&header, width).bold());
+        if self.hits.is_empty() {
+            move_to(3, BEGINNING_ROW);
+            if !self.searching {
+                print!("No match.");
+            }
+            return Ok(());
+        }
+
+        //Hits in the upper half, and the preview in the lower half.
+        let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+        let list_rows = (rows / 2).max(1);
+        let skip = (self.index + 1).saturating_sub(list_rows);
+        for (i, hit) in self.hits.iter().enumerate().skip(skip).take(list_rows) {
+            let path = hit.path.strip_prefix(&self.root).unwrap_or(&hit.path);
+            let line = format!("{}:{}: {}", path.display(), hit.line, hit.text);
+            let line = truncate_to_width(&line, width.saturating_sub(3));
+            move_to(1, (i - skip) as u16 + BEGINNING_ROW);
+            if i == self.index {
+                print!("> {}", line.negative());
+            } else {
+                print!("  {}", line);
+            }
+        }
+
+        let preview_rows = rows.saturating_sub(list_rows + 1);
+        if self.preview.as_ref().map(|(i, _)| *i) != Some(self.index) {
+            let lines = preview(layout, &self.hits[self.index], preview_rows, width);
+            self.preview = Some((self.index, lines));
+        }
+        if let Some((_, lines)) = &self.preview {
+            let start = BEGINNING_ROW as usize + list_rows + 1;
+            for (i, line) in lines.iter().enumerate() {
+                move_to(1, (start + i) as u16);
+                print!("{}", line);
+                reset_color();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Search the text files under the root, sending the matching lines until the view is closed.
+fn search(root: &Path, matcher: &Matcher, show_hidden: bool, sender: Sender<Hit>) {
+    let mut count = 0;
+    walk(root, show_hidden, |entry| {
+        if !entry.file_type().is_file() {
+            return true;
+        }
+        for hit in search_file(entry.path(), matcher) {
+            count += 1;
+            if sender.send(hit).is_err() || count >= MAX_HITS {
+                return false;
+            }
+        }
+        true
+    });
+}
+
+/// Matching lines in the file, skipping binaries as in the preview.
+fn search_file(p: &Path, matcher: &Matcher) -> Vec<Hit> {
+    match std::fs::metadata(p) {
+        Ok(metadata) if metadata.len() <= MAX_SIZE_TO_SEARCH => {}
+        _ => return vec![],
+    }
+    let content = match std::fs::read(p) {
+        Ok(content) => content,
+        Err(_) => return vec![],
+    };
+    if !content_inspector::inspect(&content).is_text() {
+        return vec![];
+    }
+    String::from_utf8_lossy(&content)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| matcher.is_match(line))
+        .map(|(i, line)| Hit {
+            path: p.to_path_buf(),
+            line: i + 1,
+            text: line.trim().replace('\t', "    "),
+        })
+        .collect()
+}
+
+/// Lines around the hit with the line numbers, highlighted if `syntax_highlight` is enabled.
+fn preview(layout: &Layout, hit: &Hit, rows: usize, width: usize) -> Vec<String> {
+    let content = match std::fs::read(&hit.path) {
+        Ok(content) => String::from_utf8_lossy(&content).replace('\t', "    "),
+        Err(e) => return vec![e.to_string()],
+    };
+    let start = hit.line.saturating_sub(rows / 2).max(1);
+    let end = start + rows;
+    let number_width = end.to_string().len();
+    let text_width = width.saturating_sub(number_width + 2);
+
+    let syntax = if layout.syntax_highlight {
+        layout
+            .syntax_set
+            .find_syntax_for_file(&hit.path)
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+    let mut highlighter = syntax.map(|syntax| HighlightLines::new(syntax, &layout.theme));
+
+    let mut result = Vec::new();
+    for (i, line) in LinesWithEndings::from(&content).enumerate() {
+        let n = i + 1;
+        if n >= end {
+            break;
+        }
+        //Lines before the start are highlighted too, to keep the state of the highlighter.
+        let ranges = match highlighter.as_mut() {
+            Some(h) => h.highlight_line(line, &layout.syntax_set).ok(),
+            None => None,
+        };
+        if n < start {
+            continue;
+        }
+
+        let number = format!("{:>width$} ", n, width = number_width);
+        let number = if n == hit.line {
+            format!("{}", number.bold().yellow())
+        } else {
+            format!("{}", number.dark_grey())
+        };
+        let text = match ranges {
+            Some(ranges) => {
+                let mut text = String::new();
+                let mut rest = text_width;
+                for (style, piece) in ranges {
+                    let piece = truncate_to_width(piece.trim_end_matches(&['\r', '\n'][..]), rest);
+                    rest -= piece.width();
+                    text.push_str(&as_24_bit_terminal_escaped(&[(style, &piece)], false));
+                    if rest == 0 {
+                        break;
+                    }
+                }
+                text
+            }
+            None => truncate_to_width(line.trim_end_matches(&['\r', '\n'][..]), text_width),
+        };
+        result.push(format!("{}{}", number, text));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let base = std::env::temp_dir().join("felix_test_grep");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("src")).unwrap();
+        std::fs::write(base.join("src/main.rs"), "fn main() {\n    todo!();\n}\n").unwrap();
+        std::fs::write(base.join("notes.txt"), "TODO: write tests\ntodo\n").unwrap();
+        std::fs::write(base.join("binary"), b"todo\x00\x01\x02").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let matcher = Matcher::new("todo", SearchMode::Regex).unwrap();
+        search(&base, &matcher, false, sender);
+        let hits: Vec<Hit> = receiver.iter().collect();
+        assert_eq!(
+            hits,
+            vec![
+                Hit {
+                    path: base.join("notes.txt"),
+                    line: 1,
+                    text: "TODO: write tests".to_owned()
+                },
+                Hit {
+                    path: base.join("notes.txt"),
+                    line: 2,
+                    text: "todo".to_owned()
+                },
+                Hit {
+                    path: base.join("src/main.rs"),
+                    line: 2,
+                    text: "todo!();".to_owned()
+                },
+            ]
+        );
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
-/// Help text.
-pub const HELP: &str = "# felix v2.2.2
+use super::keymap::Keymap;
+
+/// Help text before the key manual.
+const HELP_HEAD: &str = "# felix v2.2.2
 A simple TUI file manager with vim-like keymapping.
 
 ## Usage
@@ -13,43 +15,24 @@ Both relative and absolute path available.
 `-l` | `--log` => Launch the app, automatically generating a log file.
 
 ## Manual
-j / Down          :Go down.
-k / Up            :Go up.
-h / Left          :Go to the parent directory if exists.
-l / Right / Enter :Open a file or change directory.
-o                 :Open a file in a new window.
-e                 :Unpack archive/compressed file.
-gg                :Go to the top.
-G                 :Go to the bottom.
-z + Enter         :Go to the home directory.
-z <keyword>       :Jump to a directory that matches the keyword. (zoxide required)
-dd                :Delete and yank one item.
-yy                :Yank one item.
-p                 :Put yanked item(s) in the current directory.
-V                 :Switch to the select mode.
-  - d             :In the select mode, delete and yank selected item(s).
-  - y             :In the select mode, yank selected item(s).
-u                 :Undo put/delete/rename.
-Ctrl + r          :Redo put/delete/rename.
-v                 :Toggle whether to show the preview.
-s                 :Toggle between vertical / horizontal split in the preview mode.
-Alt + j / Down    :Scroll down the preview text.
-Alt + k / Up      :Scroll up the preview text.
-backspace         :Toggle whether to show hidden items.
-t                 :Toggle the sort order (name <-> modified time).
-:                 :Switch to the shell mode.
-c                 :Switch to the rename mode.
-/                 :Search items by the keyword.
-n                 :Go forward to the item that matches the keyword.
-N                 :Go backward to the item that matches the keyword.
-Esc               :Return to the normal mode.
-:cd / :z          :Go to the home directory.
+";
+
+/// Help text after the key manual.
+const HELP_TAIL: &str = ":cd / :z          :Go to the home directory.
 :z <keyword>      :Same as `z <keyword>`.
 :e                :Reload the current directory.
-:trash            :Go to the trash directory.
+:rename <pattern> :Rename the selected item(s) (or the current item) by `s/regex/replacement/(g)` or a template such as `photo_{n:03}.{ext}` ({n}, {name}, {stem}, {ext}, {mtime:%Y%m%d}), after the preview and confirmation.
+:grep <regex>     :Search the contents of the text files under the current directory. In the results, Enter opens the file at the line in the default command.
+:sort <key>       :Sort by name, time, size, extension, created, accessed or type.
+:view save        :Save the current view (sort key, reverse, hidden items and preview) for the current directory, applied whenever you enter it.
+:view reset       :Remove the view saved for the current directory.
+%s / %f / %d      :In the shell mode, replaced with the selected item(s) (or the current item) / the current item / the current directory. `$fx_selected` (newline-separated) and `$fx_current` are also set.
+:!<command>       :Execute the command and show the output in the pager (j/k: scroll, f/b: page, g/G: top/bottom).
+:<name>           :Run the custom command defined in `custom_commands` of the config file.
+:trash            :Show the trash view: r to restore an item to the original directory, p to restore it to the current directory, D to delete it permanently.
 :empty            :Empty the trash directory.
 :h                :Show help.
-:q / ZZ           :Exit.
+:q                :Exit.
 
 ## Preview feature
 By default, text files and directories can be previewed.
@@ -74,3 +57,12 @@ log files       : $PROFILE\\AppData\\Local\\felix\\log
 
 For more details, visit https://github.com/kyoheiu/felix
 ";
+
+/// Generate the help text, including the key manual of the active keymap.
+pub fn help_text(keymap: &Keymap) -> String {
+    let mut help = HELP_HEAD.to_owned();
+    help.push_str(&keymap.to_manual());
+    help.push_str("Esc               :Return to the normal mode.\n");
+    help.push_str(HELP_TAIL);
+    help
+}
@@ -0,0 +1,725 @@
+use super::errors::FxError;
+use super::functions::*;
+use super::magic_packed;
+use super::op::ConflictPolicy;
+use super::state::ItemInfo;
+
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval to check the running jobs while waiting for key input.
+pub const JOB_POLL_INTERVAL: Duration = Duration::from_millis(200);
+pub const JOBS_RUNNING_WARNING: &str =
+    "Some jobs are still running. Wait or cancel them in the jobs view.";
+/// Files are copied in chunks of this size, to report the progress and check the cancellation.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Progress of a job, shared with the worker thread.
+#[derive(Debug, Default)]
+pub struct Progress {
+    bytes: AtomicU64,
+    total_bytes: AtomicU64,
+    files: AtomicU64,
+    total_files: AtomicU64,
+    counted: AtomicBool,
+    cancelled: AtomicBool,
+    started: Mutex<Option<Instant>>,
+}
+
+/// Copy `from` to `to` if `to` is set, and then remove `from` if `remove` is true.
+/// If `backup` is set, the item at `to` is moved there before the copy.
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub from: PathBuf,
+    pub to: Option<PathBuf>,
+    pub remove: bool,
+    pub backup: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobKind {
+    Put {
+        original: Vec<ItemInfo>,
+        dir: PathBuf,
+        policy: Vec<Option<ConflictPolicy>>,
+    },
+    Delete {
+        original: Vec<ItemInfo>,
+        dir: PathBuf,
+        /// Registered items and whether they are cut, when the job is spawned.
+        registered: Vec<ItemInfo>,
+        cut: bool,
+    },
+    /// Move cut items, by rename on the same filesystem or by copy and remove across devices.
+    Move {
+        original: Vec<PathBuf>,
+        dir: PathBuf,
+    },
+    Unpack {
+        archive: PathBuf,
+        dest: PathBuf,
+    },
+    /// Custom command run in the background.
+    Command {
+        name: String,
+        program: String,
+        args: Vec<String>,
+        envs: Vec<(&'static str, String)>,
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum JobStatus {
+    Running,
+    Done(Duration),
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    tasks: Vec<Task>,
+    progress: Arc<Progress>,
+    start: Instant,
+    receiver: Receiver<(usize, Result<(), FxError>)>,
+}
+
+/// Result of a finished job, to be applied to the state.
+#[derive(Debug)]
+pub struct Finished {
+    pub kind: JobKind,
+    pub done: Vec<Task>,
+    /// Tasks not done because of an error or the cancellation.
+    pub rest: Vec<Task>,
+    pub status: JobStatus,
+}
+
+/// Jobs running on worker threads, and finished ones to be listed in the jobs view.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    pub list: Vec<Job>,
+    next_id: usize,
+}
+
+impl Progress {
+    fn add_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn start(&self, total_bytes: u64, total_files: u64) {
+        self.total_bytes.store(total_bytes, Ordering::Relaxed);
+        self.total_files.store(total_files, Ordering::Relaxed);
+        self.counted.store(true, Ordering::Relaxed);
+        if let Ok(mut started) = self.started.lock() {
+            *started = Some(Instant::now());
+        }
+    }
+
+    /// Throughput (bytes per second) and estimated time left, after the copy is started.
+    fn speed(&self) -> Option<(u64, Option<Duration>)> {
+        let elapsed = (*self.started.lock().ok()?)?.elapsed();
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let total_bytes = self.total_bytes.load(Ordering::Relaxed);
+        let rate = (bytes as f64 / elapsed.as_secs_f64()) as u64;
+        Some((rate, eta(bytes, total_bytes, rate)))
+    }
+
+    /// Percentage, throughput and ETA, printed on the info line.
+    pub fn to_detail(&self) -> String {
+        match self.speed() {
+            Some((rate, eta)) => {
+                let eta = match eta {
+                    Some(eta) => format_eta(eta),
+                    None => "--".to_owned(),
+                };
+                format!("{} {}/s ETA {}", self, to_proper_size(rate), eta)
+            }
+            None => self.to_string(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Count total bytes and files to copy before starting.
+    /// Returns bytes and files of each task, to count them at once when the item is just renamed.
+    fn count(&self, tasks: &[Task]) -> Vec<(u64, u64)> {
+        let sizes: Vec<(u64, u64)> = tasks
+            .iter()
+            .map(|task| match task.to {
+                Some(_) => count_item(&task.from),
+                None => (0, 0),
+            })
+            .collect();
+        self.start(
+            sizes.iter().map(|(bytes, _)| bytes).sum(),
+            sizes.iter().map(|(_, files)| files).sum(),
+        );
+        sizes
+    }
+}
+
+impl std::fmt::Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if !self.counted.load(Ordering::Relaxed) {
+            return write!(f, "counting...");
+        }
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let total_bytes = self.total_bytes.load(Ordering::Relaxed);
+        let files = self.files.load(Ordering::Relaxed);
+        let total_files = self.total_files.load(Ordering::Relaxed);
+        let percent = (bytes * 100).checked_div(total_bytes).unwrap_or(100);
+        write!(
+            f,
+            "{}% {}/{} files {}/{}",
+            percent,
+            files,
+            total_files,
+            to_proper_size(bytes),
+            to_proper_size(total_bytes)
+        )
+    }
+}
+
+impl JobKind {
+    pub fn name(&self) -> &str {
+        match self {
+            JobKind::Put { .. } => "PUT",
+            JobKind::Delete { .. } => "DELETE",
+            JobKind::Move { .. } => "MOVE",
+            JobKind::Unpack { .. } => "UNPACK",
+            JobKind::Command { .. } => "CMD",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            JobKind::Put { original, dir, .. } => {
+                format!("{} -> {}", count_items(original.len()), dir.display())
+            }
+            JobKind::Delete { original, dir, .. } => {
+                format!("{} in {}", count_items(original.len()), dir.display())
+            }
+            JobKind::Move { original, dir } => {
+                format!("{} -> {}", count_items(original.len()), dir.display())
+            }
+            JobKind::Unpack { archive, dest } => {
+                format!("{} -> {}", archive.display(), dest.display())
+            }
+            JobKind::Command { name, dir, .. } => format!("{} in {}", name, dir.display()),
+        }
+    }
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Done(duration) => write!(f, "done [{}]", duration_to_string(*duration)),
+            JobStatus::Failed(e) => write!(f, "failed: {}", e),
+            JobStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl Job {
+    /// Line in the jobs view.
+    pub fn to_line(&self) -> String {
+        format!(
+            "#{} {:<6} {} {} {}",
+            self.id,
+            self.kind.name(),
+            self.status,
+            self.progress_text(),
+            self.kind.description()
+        )
+    }
+
+    /// Progress of the copy, or the elapsed time of the command which has no progress.
+    fn progress_text(&self) -> String {
+        match self.kind {
+            JobKind::Command { .. } => duration_to_string(self.start.elapsed()),
+            _ => self.progress.to_string(),
+        }
+    }
+}
+
+impl Finished {
+    pub fn is_error(&self) -> bool {
+        matches!(self.status, JobStatus::Failed(_))
+    }
+
+    /// Message printed when the job is finished.
+    pub fn message(&self) -> String {
+        match &self.status {
+            JobStatus::Done(duration) => {
+                let duration = duration_to_string(*duration);
+                match self.kind {
+                    JobKind::Put { .. } => {
+                        format!("{} inserted [{}]", count_items(self.done.len()), duration)
+                    }
+                    JobKind::Delete { .. } => {
+                        format!("{} deleted [{}]", count_items(self.done.len()), duration)
+                    }
+                    JobKind::Move { .. } => {
+                        format!("{} moved [{}]", count_items(self.done.len()), duration)
+                    }
+                    JobKind::Unpack { .. } => format!("Unpacked. [{}]", duration),
+                    JobKind::Command { ref name, .. } => {
+                        format!("{} finished. [{}]", name, duration)
+                    }
+                }
+            }
+            JobStatus::Failed(e) => e.to_owned(),
+            status => format!("{}: {}", self.kind.name(), status),
+        }
+    }
+}
+
+impl Jobs {
+    /// Start a job on a worker thread.
+    pub fn spawn(&mut self, kind: JobKind, tasks: Vec<Task>) {
+        let progress = Arc::new(Progress::default());
+        let (sender, receiver) = mpsc::channel();
+
+        let worker_kind = kind.clone();
+        let worker_tasks = tasks.clone();
+        let worker_progress = progress.clone();
+        thread::spawn(move || {
+            let result = match worker_kind {
+                JobKind::Unpack { archive, dest } => {
+                    unpack_archive(&archive, &dest, &worker_progress)
+                }
+                JobKind::Command {
+                    program,
+                    args,
+                    envs,
+                    dir,
+                    ..
+                } => run_command(&program, &args, envs, &dir, &worker_progress),
+                _ => run_tasks(&worker_tasks, &worker_progress),
+            };
+            let _ = sender.send(result);
+        });
+
+        self.next_id += 1;
+        self.list.push(Job {
+            id: self.next_id,
+            kind,
+            status: JobStatus::Running,
+            tasks,
+            progress,
+            start: Instant::now(),
+            receiver,
+        });
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.list
+            .iter()
+            .any(|job| matches!(job.status, JobStatus::Running))
+    }
+
+    /// Cancel the job if running. Cancellation takes effect between files.
+    pub fn cancel(&self, i: usize) {
+        if let Some(job) = self.list.get(i) {
+            if let JobStatus::Running = job.status {
+                job.progress.cancel();
+            }
+        }
+    }
+
+    /// Collect jobs finished since the last call.
+    pub fn collect(&mut self) -> Vec<Finished> {
+        let mut finished = Vec::new();
+        for job in self.list.iter_mut() {
+            if let JobStatus::Running = job.status {
+                if let Ok((done, result)) = job.receiver.try_recv() {
+                    job.status = match result {
+                        Ok(_) => JobStatus::Done(job.start.elapsed()),
+                        Err(FxError::Cancelled) => JobStatus::Cancelled,
+                        Err(e) => JobStatus::Failed(e.to_string()),
+                    };
+                    finished.push(Finished {
+                        kind: job.kind.clone(),
+                        done: job.tasks[..done].to_vec(),
+                        rest: job.tasks[done..].to_vec(),
+                        status: job.status.clone(),
+                    });
+                }
+            }
+        }
+        finished
+    }
+
+    /// Progress of the first running job in detail, shown in the info line.
+    pub fn detail(&self) -> Option<String> {
+        let job = self
+            .list
+            .iter()
+            .find(|job| matches!(job.status, JobStatus::Running))?;
+        let detail = match job.kind {
+            JobKind::Command { .. } => job.progress_text(),
+            _ => job.progress.to_detail(),
+        };
+        Some(format!("{}: {}", job.kind.name(), detail))
+    }
+
+    /// Progress of the running jobs, shown in the status line.
+    pub fn summary(&self) -> Option<String> {
+        let mut running = self
+            .list
+            .iter()
+            .filter(|job| matches!(job.status, JobStatus::Running));
+        let first = running.next()?;
+        let others = running.count();
+        if others == 0 {
+            Some(format!(" {} {} ", first.kind.name(), first.progress_text()))
+        } else {
+            Some(format!(
+                " {} {} (+{}) ",
+                first.kind.name(),
+                first.progress_text(),
+                others
+            ))
+        }
+    }
+}
+
+/// Run the tasks in order, until all of them are done, any of them fails or the job is cancelled.
+/// Returns the number of tasks done, with the error if any.
+pub fn run_tasks(tasks: &[Task], progress: &Progress) -> (usize, Result<(), FxError>) {
+    let sizes = progress.count(tasks);
+    for (i, task) in tasks.iter().enumerate() {
+        if let Some(to) = &task.to {
+            if let Some(backup) = &task.backup {
+                if let Err(e) = move_item(to, backup) {
+                    return (i, Err(e));
+                }
+            }
+            //Moving on the same filesystem is just a rename.
+            //Only across devices, it falls back to copy and remove.
+            if task.remove {
+                match std::fs::rename(&task.from, to) {
+                    Ok(_) => {
+                        let (bytes, files) = sizes[i];
+                        progress.add_bytes(bytes);
+                        progress.files.fetch_add(files, Ordering::Relaxed);
+                        continue;
+                    }
+                    Err(e) if is_same_device(&task.from, to) => {
+                        if let Some(backup) = &task.backup {
+                            let _ = move_item(backup, to);
+                        }
+                        return (i, Err(e.into()));
+                    }
+                    Err(_) => {}
+                }
+            }
+            if let Err(e) = copy_item(&task.from, to, progress) {
+                //Remove the incomplete copy, and restore the overwritten item.
+                let _ = remove_item(to);
+                if let Some(backup) = &task.backup {
+                    let _ = move_item(backup, to);
+                }
+                return (i, Err(e));
+            }
+        }
+        //Once copied, the task is counted as done even if the removal fails.
+        if task.remove && remove_item(&task.from).is_err() {
+            let done = if task.to.is_some() { i + 1 } else { i };
+            return (done, Err(FxError::RemoveItem(task.from.clone())));
+        }
+    }
+    (tasks.len(), Ok(()))
+}
+
+/// Unpack the archive. It cannot be interrupted, so the output is removed if cancelled meanwhile.
+fn unpack_archive(
+    archive: &Path,
+    dest: &Path,
+    progress: &Progress,
+) -> (usize, Result<(), FxError>) {
+    let size = std::fs::metadata(archive).map(|m| m.len()).unwrap_or(0);
+    progress.start(size, 1);
+
+    if let Err(e) = magic_packed::unpack(archive, dest) {
+        //The destination reserved beforehand is removed if nothing is unpacked.
+        let _ = std::fs::remove_dir(dest);
+        return (0, Err(e));
+    }
+    if progress.is_cancelled() {
+        let _ = remove_item(dest);
+        return (0, Err(FxError::Cancelled));
+    }
+    progress.add_bytes(size);
+    progress.add_file();
+    (0, Ok(()))
+}
+
+/// Run the command without the terminal, killing it if the job is cancelled.
+fn run_command(
+    program: &str,
+    args: &[String],
+    envs: Vec<(&'static str, String)>,
+    dir: &Path,
+    progress: &Progress,
+) -> (usize, Result<(), FxError>) {
+    let child = std::process::Command::new(program)
+        .args(args)
+        .envs(envs)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return (0, Err(FxError::Command(format!("{}: {}", program, e)))),
+    };
+    loop {
+        if progress.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return (0, Err(FxError::Cancelled));
+        }
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return (0, Ok(())),
+            Ok(Some(status)) => {
+                return (0, Err(FxError::Command(format!("{}: {}", program, status))));
+            }
+            Ok(None) => thread::sleep(JOB_POLL_INTERVAL),
+            Err(e) => return (0, Err(e.into())),
+        }
+    }
+}
+
+/// Copy an item recursively, keeping symlinks as they are.
+pub fn copy_item(from: &Path, to: &Path, progress: &Progress) -> Result<(), FxError> {
+    if is_symlink(from) {
+        copy_symlink(from, to)?;
+        progress.add_file();
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(from) {
+        if progress.is_cancelled() {
+            return Err(FxError::Cancelled);
+        }
+        let entry = entry?;
+        let entry_path = entry.path();
+        let target = match entry_path.strip_prefix(from) {
+            Ok(child) if child.as_os_str().is_empty() => to.to_path_buf(),
+            Ok(child) => to.join(child),
+            Err(_) => return Err(FxError::PutItem(entry_path.to_owned())),
+        };
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_symlink() {
+            copy_symlink(entry_path, &target)?;
+            progress.add_file();
+        } else {
+            copy_file(entry_path, &target, progress)?;
+            progress.add_file();
+        }
+    }
+    Ok(())
+}
+
+/// Copy a file in chunks, reporting the copied bytes.
+fn copy_file(from: &Path, to: &Path, progress: &Progress) -> Result<(), FxError> {
+    let put_error = |_| FxError::PutItem(from.to_owned());
+    let mut reader = File::open(from).map_err(put_error)?;
+    let mut writer = File::create(to).map_err(put_error)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        if progress.is_cancelled() {
+            return Err(FxError::Cancelled);
+        }
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(put_error(e)),
+        };
+        writer.write_all(&buf[..n]).map_err(put_error)?;
+        progress.add_bytes(n as u64);
+    }
+    //Keep the permissions as `std::fs::copy` does.
+    let permissions = reader.metadata().map_err(put_error)?.permissions();
+    writer.set_permissions(permissions).map_err(put_error)?;
+    Ok(())
+}
+
+/// Estimated time left, if the throughput is known.
+fn eta(bytes: u64, total_bytes: u64, rate: u64) -> Option<Duration> {
+    let left = total_bytes.saturating_sub(bytes);
+    left.checked_div(rate).map(Duration::from_secs)
+}
+
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Bytes and files of the item to copy.
+fn count_item(p: &Path) -> (u64, u64) {
+    if is_symlink(p) {
+        return (0, 1);
+    }
+    let mut bytes = 0;
+    let mut files = 0;
+    for entry in walkdir::WalkDir::new(p).into_iter().flatten() {
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        files += 1;
+        if let Ok(metadata) = entry.metadata() {
+            bytes += metadata.len();
+        }
+    }
+    (bytes, files)
+}
+
+/// Remove a file, symlink or directory recursively.
+pub fn remove_item(p: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(p)?.is_dir() {
+        std::fs::remove_dir_all(p)
+    } else {
+        std::fs::remove_file(p)
+    }
+}
+
+fn is_symlink(p: &Path) -> bool {
+    std::fs::symlink_metadata(p)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+#[cfg(target_family = "unix")]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), FxError> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)?;
+    Ok(())
+}
+
+#[cfg(not(target_family = "unix"))]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), FxError> {
+    if std::fs::copy(from, to).is_err() {
+        return Err(FxError::PutItem(from.to_owned()));
+    }
+    Ok(())
+}
+
+fn count_items(n: usize) -> String {
+    if n == 1 {
+        "1 item".to_owned()
+    } else {
+        format!("{} items", n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_tasks() {
+        let base = std::env::temp_dir().join("felix_test_run_tasks");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("from/child")).unwrap();
+        std::fs::write(base.join("from/child/a.txt"), "abc").unwrap();
+        std::fs::write(base.join("b.txt"), "de").unwrap();
+
+        let tasks = vec![
+            Task {
+                from: base.join("from"),
+                to: Some(base.join("to")),
+                remove: false,
+                backup: None,
+            },
+            Task {
+                from: base.join("b.txt"),
+                to: Some(base.join("c.txt")),
+                remove: true,
+                backup: None,
+            },
+        ];
+        let progress = Progress::default();
+        let (done, result) = run_tasks(&tasks, &progress);
+        assert!(result.is_ok());
+        assert_eq!(done, 2);
+        assert_eq!(progress.to_string(), "100% 2/2 files 5B/5B");
+        assert!(base.join("from/child/a.txt").exists());
+        assert!(base.join("to/child/a.txt").exists());
+        assert!(!base.join("b.txt").exists());
+        assert!(base.join("c.txt").exists());
+
+        //Overwrite the item, moving the original to the backup path.
+        std::fs::write(base.join("d.txt"), "new").unwrap();
+        let tasks = vec![Task {
+            from: base.join("d.txt"),
+            to: Some(base.join("c.txt")),
+            remove: false,
+            backup: Some(base.join("backup.txt")),
+        }];
+        let (done, result) = run_tasks(&tasks, &Progress::default());
+        assert!(result.is_ok());
+        assert_eq!(done, 1);
+        assert_eq!(std::fs::read_to_string(base.join("c.txt")).unwrap(), "new");
+        assert_eq!(
+            std::fs::read_to_string(base.join("backup.txt")).unwrap(),
+            "de"
+        );
+
+        //Cancelled before starting.
+        let progress = Progress::default();
+        progress.cancel();
+        let tasks = vec![Task {
+            from: base.join("from"),
+            to: Some(base.join("cancelled")),
+            remove: false,
+            backup: None,
+        }];
+        let (done, result) = run_tasks(&tasks, &progress);
+        assert!(matches!(result, Err(FxError::Cancelled)));
+        assert_eq!(done, 0);
+        assert!(!base.join("cancelled").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(0, 100, 10), Some(Duration::from_secs(10)));
+        assert_eq!(eta(100, 100, 10), Some(Duration::from_secs(0)));
+        assert_eq!(eta(0, 100, 0), None);
+        assert_eq!(format_eta(Duration::from_secs(42)), "42s");
+        assert_eq!(format_eta(Duration::from_secs(185)), "3m05s");
+        assert_eq!(format_eta(Duration::from_secs(7320)), "2h02m");
+    }
+}
@@ -0,0 +1,718 @@
+use super::errors::FxError;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashMap};
+
+/// Named actions which can be bound to key sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Down,
+    Up,
+    Parent,
+    Open,
+    OpenNewWindow,
+    Unpack,
+    GoTop,
+    GoBottom,
+    Zoxide,
+    Delete,
+    Yank,
+    Cut,
+    Put,
+    Select,
+    Undo,
+    Redo,
+    TogglePreview,
+    ToggleSplit,
+    ScrollPreviewDown,
+    ScrollPreviewUp,
+    ToggleHidden,
+    ToggleSort,
+    ReverseSort,
+    ToggleDirsFirst,
+    Shell,
+    Rename,
+    BulkRename,
+    EditPermissions,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Filter,
+    Find,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    ToggleDualPane,
+    SwitchPane,
+    Jobs,
+    Quit,
+    /// Run the line in the shell mode, such as a custom command. Index of `Keymap::lines`.
+    Command(usize),
+    /// Used in the config to unbind a default key sequence.
+    Nop,
+}
+
+/// Action name in the config, action and description for the help text.
+/// The order is also used to generate the help text.
+const ACTIONS: &[(&str, Action, &str)] = &[
+    ("down", Action::Down, "Go down."),
+    ("up", Action::Up, "Go up."),
+    (
+        "parent",
+        Action::Parent,
+        "Go to the parent directory if exists.",
+    ),
+    ("open", Action::Open, "Open a file or change directory."),
+    (
+        "open_new_window",
+        Action::OpenNewWindow,
+        "Open a file in a new window.",
+    ),
+    ("unpack", Action::Unpack, "Unpack archive/compressed file."),
+    ("go_top", Action::GoTop, "Go to the top."),
+    ("go_bottom", Action::GoBottom, "Go to the bottom."),
+    (
+        "zoxide",
+        Action::Zoxide,
+        "Go to the home directory by Enter, or jump to a directory that matches <keyword>. (zoxide required)",
+    ),
+    ("delete", Action::Delete, "Delete and yank one item."),
+    ("yank", Action::Yank, "Yank one item."),
+    ("cut", Action::Cut, "Cut one item, to move it by put."),
+    (
+        "put",
+        Action::Put,
+        "Put yanked item(s) in the current directory, or move cut item(s).",
+    ),
+    ("select", Action::Select, "Switch to the select mode."),
+    ("undo", Action::Undo, "Undo put/delete/rename/move."),
+    ("redo", Action::Redo, "Redo put/delete/rename/move."),
+    (
+        "toggle_preview",
+        Action::TogglePreview,
+        "Toggle whether to show the preview.",
+    ),
+    (
+        "toggle_split",
+        Action::ToggleSplit,
+        "Toggle between vertical / horizontal split in the preview mode.",
+    ),
+    (
+        "scroll_preview_down",
+        Action::ScrollPreviewDown,
+        "Scroll down the preview text.",
+    ),
+    (
+        "scroll_preview_up",
+        Action::ScrollPreviewUp,
+        "Scroll up the preview text.",
+    ),
+    (
+        "toggle_hidden",
+        Action::ToggleHidden,
+        "Toggle whether to show hidden items.",
+    ),
+    (
+        "toggle_sort",
+        Action::ToggleSort,
+        "Switch the sort key (name -> modified time -> size -> extension -> created time -> accessed time -> type).",
+    ),
+    (
+        "reverse_sort",
+        Action::ReverseSort,
+        "Reverse the sort order.",
+    ),
+    (
+        "toggle_dirs_first",
+        Action::ToggleDirsFirst,
+        "Toggle whether to list directories first or mix them with files.",
+    ),
+    ("shell", Action::Shell, "Switch to the shell mode."),
+    ("rename", Action::Rename, "Switch to the rename mode."),
+    (
+        "bulk_rename",
+        Action::BulkRename,
+        "Rename the selected item(s), or the current item, in the editor.",
+    ),
+    (
+        "edit_permissions",
+        Action::EditPermissions,
+        "Edit the permissions and owner of the item (space: toggle the bit, o: octal, u: user, g: group, Enter: apply).",
+    ),
+    (
+        "search",
+        Action::Search,
+        "Search items by the keyword, case-insensitive unless it contains an upper case letter. Tab switches the mode (substring / regex / glob).",
+    ),
+    (
+        "search_next",
+        Action::SearchNext,
+        "Go forward to the item that matches the keyword.",
+    ),
+    (
+        "search_previous",
+        Action::SearchPrevious,
+        "Go backward to the item that matches the keyword.",
+    ),
+    (
+        "filter",
+        Action::Filter,
+        "Show only the items that match the keyword, until the directory changes. Tab switches the mode as in the search, and an empty keyword clears the filter.",
+    ),
+    (
+        "find",
+        Action::Find,
+        "Find items under the current directory by fuzzy matching, and jump to the selected one.",
+    ),
+    (
+        "new_tab",
+        Action::NewTab,
+        "Open a new tab in the current directory.",
+    ),
+    ("close_tab", Action::CloseTab, "Close the current tab."),
+    ("next_tab", Action::NextTab, "Go to the next tab."),
+    ("previous_tab", Action::PreviousTab, "Go to the previous tab."),
+    (
+        "toggle_dual_pane",
+        Action::ToggleDualPane,
+        "Toggle the dual-pane mode. In the dual-pane mode, put items in the other pane.",
+    ),
+    (
+        "switch_pane",
+        Action::SwitchPane,
+        "Move the focus to the other pane in the dual-pane mode.",
+    ),
+    (
+        "jobs",
+        Action::Jobs,
+        "Show the running/finished jobs. In the jobs view, d cancels the job.",
+    ),
+    ("quit", Action::Quit, "Exit."),
+    ("none", Action::Nop, ""),
+];
+
+/// Default key bindings.
+const DEFAULT_KEYMAP: &[(&str, Action)] = &[
+    ("j", Action::Down),
+    ("<Down>", Action::Down),
+    ("k", Action::Up),
+    ("<Up>", Action::Up),
+    ("h", Action::Parent),
+    ("<Left>", Action::Parent),
+    ("l", Action::Open),
+    ("<Right>", Action::Open),
+    ("<Enter>", Action::Open),
+    ("o", Action::OpenNewWindow),
+    ("e", Action::Unpack),
+    ("gg", Action::GoTop),
+    ("G", Action::GoBottom),
+    ("z", Action::Zoxide),
+    ("dd", Action::Delete),
+    ("yy", Action::Yank),
+    ("xx", Action::Cut),
+    ("p", Action::Put),
+    ("V", Action::Select),
+    ("u", Action::Undo),
+    ("<C-r>", Action::Redo),
+    ("v", Action::TogglePreview),
+    ("s", Action::ToggleSplit),
+    ("<A-j>", Action::ScrollPreviewDown),
+    ("<A-Down>", Action::ScrollPreviewDown),
+    ("<A-k>", Action::ScrollPreviewUp),
+    ("<A-Up>", Action::ScrollPreviewUp),
+    ("<BS>", Action::ToggleHidden),
+    ("t", Action::ToggleSort),
+    ("T", Action::ReverseSort),
+    (":", Action::Shell),
+    ("c", Action::Rename),
+    ("C", Action::BulkRename),
+    ("M", Action::EditPermissions),
+    ("/", Action::Search),
+    ("n", Action::SearchNext),
+    ("N", Action::SearchPrevious),
+    ("f", Action::Filter),
+    ("F", Action::Find),
+    ("<C-t>", Action::NewTab),
+    ("<C-w>", Action::CloseTab),
+    ("gt", Action::NextTab),
+    ("gT", Action::PreviousTab),
+    ("w", Action::ToggleDualPane),
+    ("<Tab>", Action::SwitchPane),
+    ("J", Action::Jobs),
+    ("ZZ", Action::Quit),
+];
+
+/// Default key bindings in the select mode.
+/// Keys not bound here fall back to the movement bindings of the normal mode.
+const DEFAULT_SELECT_KEYMAP: &[(&str, Action)] = &[
+    ("d", Action::Delete),
+    ("y", Action::Yank),
+    ("x", Action::Cut),
+    ("c", Action::BulkRename),
+    ("r", Action::Rename),
+    ("m", Action::EditPermissions),
+    (":", Action::Shell),
+    ("<Esc>", Action::Select),
+];
+
+/// Actions available in the select mode, and the description for the help text.
+const SELECT_ACTIONS: &[(Action, &str)] = &[
+    (
+        Action::Delete,
+        "In the select mode, delete and yank selected item(s).",
+    ),
+    (Action::Yank, "In the select mode, yank selected item(s)."),
+    (Action::Cut, "In the select mode, cut selected item(s)."),
+    (
+        Action::BulkRename,
+        "In the select mode, rename selected item(s) at once in the editor.",
+    ),
+    (
+        Action::Rename,
+        "In the select mode, rename selected item(s) by the substitution or template (same as `:rename`).",
+    ),
+    (
+        Action::EditPermissions,
+        "In the select mode, edit the permissions and owner of selected item(s).",
+    ),
+    (
+        Action::Shell,
+        "In the select mode, switch to the shell mode with the selected item(s) as `%s`.",
+    ),
+];
+
+/// A single key press, normalized so that it can be compared with the one in the keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+/// Result of looking up the pending key sequence.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    /// The sequence is bound to the action.
+    Matched(Action),
+    /// The sequence is a prefix of one or more bindings (e.g. `g` of `gg`).
+    Pending,
+    /// The sequence is not bound to anything.
+    NotMatched,
+}
+
+/// Key sequences and the actions bound to them.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    map: HashMap<Vec<KeyPress>, Action>,
+    /// Bindings in the select mode.
+    select: HashMap<Vec<KeyPress>, Action>,
+    /// Lines bound by `:<line>` in the config.
+    lines: Vec<String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut map = HashMap::new();
+        for (keys, action) in DEFAULT_KEYMAP.iter() {
+            map.insert(parse_keys(keys).unwrap(), *action);
+        }
+        let mut select = HashMap::new();
+        for (keys, action) in DEFAULT_SELECT_KEYMAP.iter() {
+            select.insert(parse_keys(keys).unwrap(), *action);
+        }
+        Keymap {
+            map,
+            select,
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        //Upper case letters and symbols already contain the shift information.
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        KeyPress {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+impl Keymap {
+    /// Make the keymap from the default bindings and the `keymap` / `select_keymap` sections in the config.
+    /// The config overrides the default; bind `none` to disable a default key sequence.
+    /// `:<line>` runs the line in the shell mode, such as `:git-add` for the custom command.
+    pub fn new(
+        config: &Option<BTreeMap<String, String>>,
+        select_config: &Option<BTreeMap<String, String>>,
+    ) -> Result<Self, FxError> {
+        let mut keymap = Keymap::default();
+        let mut configured = Vec::new();
+        if let Some(config) = config {
+            for (keys, action) in config.iter() {
+                let keys = parse_keys(keys)?;
+                let action = match action.strip_prefix(':') {
+                    Some(line) => {
+                        keymap.lines.push(line.to_owned());
+                        Action::Command(keymap.lines.len() - 1)
+                    }
+                    None => to_action(action)?,
+                };
+                if action == Action::Nop {
+                    keymap.map.remove(&keys);
+                } else {
+                    bind(&mut keymap.map, &mut configured, keys, action)?;
+                }
+            }
+        }
+        let mut configured = Vec::new();
+        if let Some(select_config) = select_config {
+            for (keys, action) in select_config.iter() {
+                let keys = parse_keys(keys)?;
+                let action = to_action(action)?;
+                if action == Action::Nop {
+                    keymap.select.remove(&keys);
+                } else {
+                    bind(&mut keymap.select, &mut configured, keys, action)?;
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Look up the key sequence typed so far.
+    pub fn lookup(&self, keys: &[KeyPress]) -> Lookup {
+        lookup_in(&self.map, keys)
+    }
+
+    /// Look up the key sequence typed so far in the select mode.
+    /// Sequences not bound in the select mode are looked up in the normal mode.
+    pub fn lookup_select(&self, keys: &[KeyPress]) -> Lookup {
+        match lookup_in(&self.select, keys) {
+            Lookup::NotMatched => lookup_in(&self.map, keys),
+            found => found,
+        }
+    }
+
+    /// Line bound to `Action::Command`.
+    pub fn line(&self, i: usize) -> &str {
+        &self.lines[i]
+    }
+
+    /// List the key sequences bound to the action, sorted by length.
+    pub fn keys_of(&self, action: Action) -> Vec<String> {
+        keys_in(&self.map, action)
+    }
+
+    /// Generate the key manual from the active keymap, used in the help text.
+    pub fn to_manual(&self) -> String {
+        let mut manual = String::new();
+        for (_, action, description) in ACTIONS.iter() {
+            if *action == Action::Nop {
+                continue;
+            }
+            let keys = self.keys_of(*action);
+            if keys.is_empty() {
+                continue;
+            }
+            let keys = keys.join(" / ");
+            manual.push_str(&format!("{:<18}:{}\n", keys, description));
+            if *action == Action::Select {
+                for (action, description) in SELECT_ACTIONS.iter() {
+                    let keys = keys_in(&self.select, *action);
+                    if keys.is_empty() {
+                        continue;
+                    }
+                    let keys = format!("  - {}", keys.join(" / "));
+                    manual.push_str(&format!("{:<18}:{}\n", keys, description));
+                }
+            }
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            let keys = self.keys_of(Action::Command(i));
+            if keys.is_empty() {
+                continue;
+            }
+            let keys = keys.join(" / ");
+            manual.push_str(&format!("{:<18}:Run `:{}`.\n", keys, line));
+        }
+        manual
+    }
+}
+
+/// Bind the key sequence in the config.
+/// Default sequences that could never be reached with it (e.g. `gg` when `g` is bound) are removed,
+/// while such a conflict between sequences in the config is an error.
+fn bind(
+    map: &mut HashMap<Vec<KeyPress>, Action>,
+    configured: &mut Vec<Vec<KeyPress>>,
+    keys: Vec<KeyPress>,
+    action: Action,
+) -> Result<(), FxError> {
+    let shadowed: Vec<Vec<KeyPress>> = map
+        .keys()
+        .filter(|bound| **bound != keys && (bound.starts_with(&keys) || keys.starts_with(bound)))
+        .cloned()
+        .collect();
+    for bound in shadowed {
+        if configured.contains(&bound) {
+            return Err(FxError::Keymap(format!(
+                "Key sequences conflict in keymap: {} and {}",
+                display_keys(&bound),
+                display_keys(&keys)
+            )));
+        }
+        map.remove(&bound);
+    }
+    configured.push(keys.clone());
+    map.insert(keys, action);
+    Ok(())
+}
+
+fn lookup_in(map: &HashMap<Vec<KeyPress>, Action>, keys: &[KeyPress]) -> Lookup {
+    if let Some(action) = map.get(keys) {
+        return Lookup::Matched(*action);
+    }
+    if map
+        .keys()
+        .any(|bound| bound.len() > keys.len() && bound.starts_with(keys))
+    {
+        Lookup::Pending
+    } else {
+        Lookup::NotMatched
+    }
+}
+
+fn keys_in(map: &HashMap<Vec<KeyPress>, Action>, action: Action) -> Vec<String> {
+    let mut keys: Vec<&Vec<KeyPress>> = map
+        .iter()
+        .filter(|(_, bound)| **bound == action)
+        .map(|(keys, _)| keys)
+        .collect();
+    keys.sort_by_key(|keys| {
+        (
+            keys.len(),
+            keys.iter().any(|key| !matches!(key.code, KeyCode::Char(_))),
+            display_keys(keys),
+        )
+    });
+    keys.into_iter().map(|keys| display_keys(keys)).collect()
+}
+
+/// Convert the action name in the config to `Action`.
+fn to_action(name: &str) -> Result<Action, FxError> {
+    ACTIONS
+        .iter()
+        .find(|(action_name, _, _)| *action_name == name)
+        .map(|(_, action, _)| *action)
+        .ok_or_else(|| FxError::Keymap(format!("Unknown action in keymap: {}", name)))
+}
+
+/// Parse a key sequence in the config, such as `gg`, `<C-r>` or `<A-Down>`.
+pub fn parse_keys(s: &str) -> Result<Vec<KeyPress>, FxError> {
+    let mut result = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '<' && chars.peek().is_some() {
+            let mut name = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == '>' && !name.is_empty() {
+                    closed = true;
+                    break;
+                }
+                name.push(c);
+            }
+            if !closed {
+                return Err(FxError::Keymap(format!("Invalid key sequence: {}", s)));
+            }
+            result
+                .push(parse_special_key(&name).ok_or_else(|| {
+                    FxError::Keymap(format!("Invalid key in keymap: <{}>", name))
+                })?);
+        } else {
+            result.push(KeyPress {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE,
+            });
+        }
+    }
+    if result.is_empty() {
+        return Err(FxError::Keymap("Empty key sequence in keymap.".to_owned()));
+    }
+    Ok(result)
+}
+
+/// Parse the key between `<` and `>`, with optional modifiers (`C-`, `A-`, `S-`).
+fn parse_special_key(name: &str) -> Option<KeyPress> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        match &rest[..1] {
+            "C" | "c" => modifiers.insert(KeyModifiers::CONTROL),
+            "A" | "a" | "M" | "m" => modifiers.insert(KeyModifiers::ALT),
+            "S" | "s" => modifiers.insert(KeyModifiers::SHIFT),
+            _ => return None,
+        }
+        rest = &rest[2..];
+    }
+
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "enter" | "cr" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "del" | "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        _ => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('f'), Some(_)) | (Some('F'), Some(_)) => KeyCode::F(rest[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+
+    //Shift is not needed for characters, as in `KeyPress::from`.
+    if let KeyCode::Char(_) = code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    Some(KeyPress { code, modifiers })
+}
+
+/// Convert the key sequence to the readable string, such as `Ctrl + r`.
+pub fn display_keys(keys: &[KeyPress]) -> String {
+    let mut result = String::new();
+    for key in keys {
+        let code = match key.code {
+            KeyCode::Char(' ') => "Space".to_owned(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Backspace => "backspace".to_owned(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            result.push_str("Ctrl + ");
+        }
+        if key.modifiers.contains(KeyModifiers::ALT) {
+            result.push_str("Alt + ");
+        }
+        if key.modifiers.contains(KeyModifiers::SHIFT) {
+            result.push_str("Shift + ");
+        }
+        result.push_str(&code);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys("gg").unwrap(),
+            vec![
+                KeyPress {
+                    code: KeyCode::Char('g'),
+                    modifiers: KeyModifiers::NONE
+                };
+                2
+            ]
+        );
+        assert_eq!(
+            parse_keys("<C-r>").unwrap(),
+            vec![KeyPress {
+                code: KeyCode::Char('r'),
+                modifiers: KeyModifiers::CONTROL
+            }]
+        );
+        assert_eq!(
+            parse_keys("<A-Down>").unwrap(),
+            vec![KeyPress {
+                code: KeyCode::Down,
+                modifiers: KeyModifiers::ALT
+            }]
+        );
+        assert!(parse_keys("<C-r").is_err());
+        assert!(parse_keys("<Foo>").is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut config = BTreeMap::new();
+        config.insert("x".to_owned(), "delete".to_owned());
+        config.insert("dd".to_owned(), "none".to_owned());
+        config.insert("ga".to_owned(), ":git-add".to_owned());
+        let mut select_config = BTreeMap::new();
+        select_config.insert("D".to_owned(), "delete".to_owned());
+        select_config.insert("d".to_owned(), "none".to_owned());
+        let keymap = Keymap::new(&Some(config), &Some(select_config)).unwrap();
+
+        let g = parse_keys("g").unwrap();
+        let gg = parse_keys("gg").unwrap();
+        assert_eq!(keymap.lookup(&g), Lookup::Pending);
+        assert_eq!(keymap.lookup(&gg), Lookup::Matched(Action::GoTop));
+        assert_eq!(
+            keymap.lookup(&parse_keys("x").unwrap()),
+            Lookup::Matched(Action::Delete)
+        );
+        assert_eq!(
+            keymap.lookup(&parse_keys("dd").unwrap()),
+            Lookup::NotMatched
+        );
+        assert_eq!(keymap.keys_of(Action::Down), vec!["j", "Down"]);
+        match keymap.lookup(&parse_keys("ga").unwrap()) {
+            Lookup::Matched(Action::Command(i)) => assert_eq!(keymap.line(i), "git-add"),
+            _ => panic!("ga is not bound to the command"),
+        }
+
+        //Default sequences shadowed by the config are removed.
+        assert_eq!(
+            keymap.lookup(&parse_keys("xx").unwrap()),
+            Lookup::NotMatched
+        );
+        let mut config = BTreeMap::new();
+        config.insert("g".to_owned(), "go_top".to_owned());
+        let shadowed = Keymap::new(&Some(config.clone()), &None).unwrap();
+        assert_eq!(shadowed.lookup(&g), Lookup::Matched(Action::GoTop));
+        assert!(shadowed.keys_of(Action::NextTab).is_empty());
+        //Conflicts in the config are an error.
+        config.insert("gx".to_owned(), "go_bottom".to_owned());
+        assert!(Keymap::new(&Some(config), &None).is_err());
+
+        //The select mode falls back to the movement bindings of the normal mode.
+        assert_eq!(
+            keymap.lookup_select(&parse_keys("D").unwrap()),
+            Lookup::Matched(Action::Delete)
+        );
+        assert_eq!(
+            keymap.lookup_select(&parse_keys("y").unwrap()),
+            Lookup::Matched(Action::Yank)
+        );
+        assert_eq!(keymap.lookup_select(&g), Lookup::Pending);
+        assert_eq!(keymap.lookup_select(&gg), Lookup::Matched(Action::GoTop));
+        assert_eq!(
+            keymap.lookup_select(&parse_keys("x").unwrap()),
+            Lookup::Matched(Action::Cut)
+        );
+    }
+}
@@ -2,7 +2,7 @@ use super::config::*;
 use super::errors::FxError;
 use super::functions::*;
 use super::nums::*;
-use super::session::SortKey;
+use super::session::{DirView, SortKey};
 use super::state::{ItemInfo, BEGINNING_ROW};
 use super::term::*;
 
@@ -19,6 +19,8 @@ pub const CHAFA_WARNING: &str =
 pub const PROPER_WIDTH: u16 = 28;
 pub const TIME_WIDTH: u16 = 16;
 const EXTRA_SPACES: u16 = 3;
+/// Columns are hidden from the right until the name has this width.
+const MIN_NAME_WIDTH: u16 = PROPER_WIDTH - TIME_WIDTH - EXTRA_SPACES;
 
 #[derive(Debug)]
 pub struct Layout {
@@ -26,10 +28,17 @@ pub struct Layout {
     pub y: u16,
     pub terminal_row: u16,
     pub terminal_column: u16,
+    pub x_offset: u16,
     pub name_max_len: usize,
-    pub time_start_pos: u16,
+    /// Columns in the config.
+    pub columns: Vec<Column>,
+    /// Columns that fit in the terminal, and their start positions.
+    pub column_starts: Vec<(Column, u16)>,
     pub colors: ConfigColor,
     pub sort_by: SortKey,
+    pub sort_reverse: bool,
+    /// Whether to list directories before files, or mix them.
+    pub dirs_first: bool,
     pub show_hidden: bool,
     pub preview: bool,
     pub split: Split,
@@ -52,6 +61,42 @@ pub enum PreviewType {
     Binary,
 }
 
+/// Column of the item list. The name is always shown first, followed by the others.
+#[derive(Debug, PartialEq, Eq, Deserialize, Clone, Copy)]
+pub enum Column {
+    Name,
+    Size,
+    /// Modified time.
+    Mtime,
+    /// `ls -l` style permissions such as `drwxr-xr-x`.
+    Permissions,
+    /// User and group.
+    Owner,
+    /// Target of the symlink.
+    LinkTarget,
+    /// Number of items in the directory.
+    Count,
+}
+
+impl Column {
+    pub fn width(self) -> u16 {
+        match self {
+            Column::Name => 0,
+            Column::Size => 6,
+            Column::Mtime => TIME_WIDTH,
+            Column::Permissions => 10,
+            Column::Owner => 17,
+            Column::LinkTarget => 24,
+            Column::Count => 6,
+        }
+    }
+
+    /// Whether to align the value to the right.
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Column::Size | Column::Count)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone, Copy)]
 pub enum Split {
     Vertical,
@@ -59,6 +104,29 @@ pub enum Split {
 }
 
 impl Layout {
+    /// Set the column offset of the item list, which is not 0 when the right pane is focused.
+    pub fn set_x_offset(&mut self, x: u16) {
+        self.x_offset = x;
+        set_pointer_column(x + 1);
+    }
+
+    /// Current view settings, to be saved for the directory.
+    pub fn view(&self) -> DirView {
+        DirView {
+            sort_by: self.sort_by,
+            sort_reverse: self.sort_reverse,
+            show_hidden: self.show_hidden,
+            preview: self.preview,
+        }
+    }
+
+    pub fn set_view(&mut self, view: &DirView) {
+        self.sort_by = view.sort_by;
+        self.sort_reverse = view.sort_reverse;
+        self.show_hidden = view.show_hidden;
+        self.preview = view.preview;
+    }
+
     /// Print preview according to the preview type.
     pub fn print_preview(&self, item: &ItemInfo, y: u16) {
         match self.split {
@@ -120,6 +188,29 @@ impl Layout {
         }
     }
 
+    /// Print the before/after names of `:rename` in the preview area.
+    pub fn print_rename_preview(&self, lines: &[String]) {
+        match self.split {
+            Split::Vertical => {
+                move_to(self.preview_start.0 - 1, 1);
+                clear_until_newline();
+                move_right(1);
+                print!("{}", split_str("[rename]", self.preview_space.0 as usize));
+                self.clear_preview(self.preview_start.0);
+            }
+            Split::Horizontal => {
+                self.clear_preview(self.preview_start.1);
+            }
+        }
+        let content = if lines.is_empty() {
+            vec!["(No items to be renamed)".to_owned()]
+        } else {
+            format_txt(&lines.join("\n"), self.preview_space.0, false)
+        };
+        self.print_txt_in_preview_area(&ItemInfo::default(), &content, false);
+        reset_color();
+    }
+
     /// Print item name at the top.
     fn print_file_name(&self, item: &ItemInfo) {
         move_to(self.preview_start.0 - 1, 1);
@@ -303,24 +394,72 @@ impl Layout {
 }
 
 /// Make app's layout according to terminal width and app's config.
-pub fn make_layout(column: u16) -> (u16, usize) {
-    let mut time_start: u16;
-    let mut name_max: usize;
+pub fn make_layout(column: u16, columns: &[Column]) -> (Vec<(Column, u16)>, usize) {
+    let mut visible: Vec<Column> = columns
+        .iter()
+        .copied()
+        .filter(|c| *c != Column::Name)
+        .collect();
+    //Each column is printed after a space, and the last one ends at the right edge.
+    let start_of = |visible: &[Column]| -> u16 {
+        (column + 1).saturating_sub(visible.iter().map(|c| c.width() + 1).sum())
+    };
+    while !visible.is_empty() && start_of(&visible) < EXTRA_SPACES + MIN_NAME_WIDTH {
+        visible.pop();
+    }
 
-    if column < PROPER_WIDTH {
-        time_start = column;
-        name_max = (column - 2).into();
-        (time_start, name_max)
-    } else {
-        time_start = column - TIME_WIDTH;
-        name_max = (time_start - EXTRA_SPACES).into();
-        let required = time_start + TIME_WIDTH - 1;
-        if required > column {
-            let diff = required - column;
-            name_max -= diff as usize;
-            time_start -= diff;
-        }
+    if visible.is_empty() {
+        return (Vec::new(), column.saturating_sub(2).into());
+    }
+
+    let mut start = start_of(&visible);
+    let name_max = (start - EXTRA_SPACES).into();
+    let mut column_starts = Vec::with_capacity(visible.len());
+    for c in visible {
+        column_starts.push((c, start));
+        start += c.width() + 1;
+    }
+    (column_starts, name_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_layout() {
+        //Same as the layout before the columns are configurable.
+        assert_eq!(
+            make_layout(80, &[Column::Mtime]),
+            (vec![(Column::Mtime, 64)], 61)
+        );
+        assert_eq!(
+            make_layout(28, &[Column::Mtime]),
+            (vec![(Column::Mtime, 12)], 9)
+        );
+        assert_eq!(make_layout(27, &[Column::Mtime]), (vec![], 25));
 
-        (time_start, name_max)
+        let columns = [
+            Column::Name,
+            Column::Size,
+            Column::Permissions,
+            Column::Mtime,
+        ];
+        assert_eq!(
+            make_layout(80, &columns),
+            (
+                vec![
+                    (Column::Size, 46),
+                    (Column::Permissions, 53),
+                    (Column::Mtime, 64)
+                ],
+                43
+            )
+        );
+        //Columns are hidden from the right on narrow terminals.
+        assert_eq!(
+            make_layout(40, &columns),
+            (vec![(Column::Size, 23), (Column::Permissions, 30)], 20)
+        );
     }
 }
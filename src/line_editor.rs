@@ -0,0 +1,220 @@
+use super::term::*;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Line editor shared by the prompts in the info line (rename, search and shell mode).
+/// The cursor moves by grapheme, and the display width is considered.
+#[derive(Debug, Clone)]
+pub struct LineEditor {
+    prompt: String,
+    text: String,
+    /// Printed after the text, such as the match of the history search.
+    suffix: String,
+    /// Byte index of the cursor, always on a grapheme boundary.
+    cursor: usize,
+}
+
+/// Result of handling a key in the line editor.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineInput {
+    Enter,
+    Cancel,
+    /// The text has been changed.
+    Changed,
+    /// Only the cursor has been moved.
+    Moved,
+    /// The key is not handled by the line editor, such as Backspace at the beginning.
+    Unhandled,
+}
+
+impl LineEditor {
+    /// Make the line editor with the initial text, with the cursor at the end.
+    pub fn new(prompt: &str, text: &str) -> Self {
+        LineEditor {
+            prompt: prompt.to_owned(),
+            text: text.to_owned(),
+            suffix: String::new(),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, moving the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_owned();
+        self.cursor = text.len();
+    }
+
+    pub fn set_suffix(&mut self, suffix: &str) {
+        self.suffix = suffix.to_owned();
+    }
+
+    /// Text before the cursor.
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Replace the text from the byte index to the cursor, moving the cursor after the replacement.
+    pub fn replace_before_cursor(&mut self, from: usize, replacement: &str) {
+        self.text.replace_range(from..self.cursor, replacement);
+        self.cursor = from + replacement.len();
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> LineInput {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => LineInput::Enter,
+            KeyCode::Esc => LineInput::Cancel,
+            KeyCode::Char('a') if ctrl => self.move_to(0),
+            KeyCode::Char('e') if ctrl => self.move_to(self.text.len()),
+            KeyCode::Char('w') if ctrl => {
+                let start = self.word_start();
+                self.delete(start, self.cursor)
+            }
+            KeyCode::Char('u') if ctrl => self.delete(0, self.cursor),
+            KeyCode::Char(_) if ctrl => LineInput::Unhandled,
+            KeyCode::Char(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                LineInput::Changed
+            }
+            KeyCode::Home => self.move_to(0),
+            KeyCode::End => self.move_to(self.text.len()),
+            KeyCode::Left => match self.previous_boundary() {
+                Some(i) => self.move_to(i),
+                None => LineInput::Moved,
+            },
+            KeyCode::Right => match self.next_boundary() {
+                Some(i) => self.move_to(i),
+                None => LineInput::Moved,
+            },
+            KeyCode::Backspace => match self.previous_boundary() {
+                Some(i) => self.delete(i, self.cursor),
+                None => LineInput::Unhandled,
+            },
+            KeyCode::Delete => match self.next_boundary() {
+                Some(i) => self.delete(self.cursor, i),
+                None => LineInput::Moved,
+            },
+            _ => LineInput::Unhandled,
+        }
+    }
+
+    /// Print the prompt and the text in the info line, and put the cursor.
+    /// If the text is too long, the part around the cursor is shown.
+    pub fn draw(&self) {
+        let (width, _) = terminal_size().unwrap_or((80, 0));
+        let space = (width as usize).saturating_sub(self.prompt.width() + 2);
+
+        //Skip graphemes from the beginning until the cursor fits in.
+        let mut start = self.cursor;
+        for (i, _) in self.text[..self.cursor].grapheme_indices(true) {
+            if self.text[i..self.cursor].width() < space {
+                start = i;
+                break;
+            }
+        }
+        let shown = truncate_to_width(&self.text[start..], space);
+        let suffix = truncate_to_width(&self.suffix, space.saturating_sub(shown.width()));
+
+        go_to_and_rest_info();
+        print!("{}{}{}", self.prompt, shown, suffix);
+        let x = 2 + self.prompt.width() + self.text[start..self.cursor].width();
+        move_to(x as u16, 2);
+    }
+
+    fn move_to(&mut self, i: usize) -> LineInput {
+        self.cursor = i;
+        LineInput::Moved
+    }
+
+    fn delete(&mut self, from: usize, to: usize) -> LineInput {
+        if from == to {
+            return LineInput::Moved;
+        }
+        self.text.replace_range(from..to, "");
+        self.cursor = from;
+        LineInput::Changed
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+    }
+
+    /// Beginning of the word before the cursor, skipping the whitespaces before it.
+    fn word_start(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        match trimmed.rfind(char::is_whitespace) {
+            Some(i) => i + trimmed[i..].chars().next().map_or(1, |c| c.len_utf8()),
+            None => 0,
+        }
+    }
+}
+
+/// Take graphemes from the beginning within the display width.
+pub fn truncate_to_width(s: &str, width: usize) -> String {
+    let mut result = String::new();
+    for g in s.graphemes(true) {
+        if result.width() + g.width() > width {
+            break;
+        }
+        result.push_str(g);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::new("New name: ", "日本語.txt");
+        editor.handle(key(KeyCode::Home));
+        editor.handle(key(KeyCode::Right));
+        editor.handle(key(KeyCode::Right));
+        editor.handle(key(KeyCode::Char('の')));
+        assert_eq!(editor.text(), "日本の語.txt");
+        editor.handle(key(KeyCode::Backspace));
+        editor.handle(key(KeyCode::Delete));
+        assert_eq!(editor.text(), "日本.txt");
+
+        //Combining character is deleted with the base character.
+        let mut editor = LineEditor::new(":", "cafe\u{301}");
+        editor.handle(key(KeyCode::Backspace));
+        assert_eq!(editor.text(), "caf");
+
+        let mut editor = LineEditor::new(":", "echo foo bar  ");
+        editor.handle(ctrl('w'));
+        assert_eq!(editor.text(), "echo foo ");
+        editor.handle(key(KeyCode::Left));
+        editor.handle(ctrl('u'));
+        assert_eq!(editor.text(), " ");
+        editor.handle(key(KeyCode::Home));
+        assert_eq!(editor.handle(key(KeyCode::Backspace)), LineInput::Unhandled);
+    }
+}
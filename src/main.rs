@@ -1,16 +1,27 @@
 mod config;
 mod errors;
+mod finder;
 mod functions;
+mod grep;
 mod help;
+mod job;
+mod keymap;
 mod layout;
+mod line_editor;
 mod magic_image;
 mod magic_packed;
+mod matcher;
 mod nums;
 mod op;
+mod permissions;
+mod rename;
 mod run;
 mod session;
+mod shell;
 mod state;
+mod tab;
 mod term;
+mod trash;
 
 use std::path::PathBuf;
 
@@ -29,7 +40,7 @@ fn main() -> Result<(), errors::FxError> {
 
         2 => match args[1].as_str() {
             "-h" | "--help" => {
-                print!("{}", help::HELP);
+                print!("{}", help::help_text(&keymap::Keymap::default()));
             }
             "-v" | "--version" => {
                 functions::check_version()?;
@@ -54,10 +65,10 @@ fn main() -> Result<(), errors::FxError> {
                     eprintln!("{}", e);
                 }
             } else {
-                print!("{}", help::HELP);
+                print!("{}", help::help_text(&keymap::Keymap::default()));
             }
         }
-        _ => print!("{}", help::HELP),
+        _ => print!("{}", help::help_text(&keymap::Keymap::default())),
     }
     Ok(())
 }
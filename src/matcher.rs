@@ -0,0 +1,127 @@
+use super::errors::FxError;
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+/// How the keyword of the search is interpreted.
+/// In every mode, the match is case-insensitive unless the keyword contains an upper case letter.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Substring of the name.
+    #[default]
+    SmartCase,
+    Regex,
+    /// Glob such as `*.rs`, matched against the whole name.
+    Glob,
+}
+
+/// Matcher of the search keyword, shared by the highlight, `n` / `N` and the match count.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    keyword: String,
+    mode: SearchMode,
+    kind: MatcherKind,
+}
+
+#[derive(Debug, Clone)]
+enum MatcherKind {
+    /// Keyword, lowercased if case-insensitive.
+    Substring(String, bool),
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+impl SearchMode {
+    /// Switched by Tab in the search mode.
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::SmartCase => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Glob,
+            SearchMode::Glob => SearchMode::SmartCase,
+        }
+    }
+
+    /// Prompt of the search mode, also shown in the footer.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            SearchMode::SmartCase => "/",
+            SearchMode::Regex => "regex/",
+            SearchMode::Glob => "glob/",
+        }
+    }
+}
+
+impl Matcher {
+    pub fn new(keyword: &str, mode: SearchMode) -> Result<Self, FxError> {
+        let ignore_case = !keyword.chars().any(char::is_uppercase);
+        let kind = match mode {
+            SearchMode::SmartCase => MatcherKind::Substring(
+                if ignore_case {
+                    keyword.to_lowercase()
+                } else {
+                    keyword.to_owned()
+                },
+                ignore_case,
+            ),
+            SearchMode::Regex => MatcherKind::Regex(
+                RegexBuilder::new(keyword)
+                    .case_insensitive(ignore_case)
+                    .build()?,
+            ),
+            SearchMode::Glob => MatcherKind::Glob(
+                GlobBuilder::new(keyword)
+                    .case_insensitive(ignore_case)
+                    .build()?
+                    .compile_matcher(),
+            ),
+        };
+        Ok(Matcher {
+            keyword: keyword.to_owned(),
+            mode,
+            kind,
+        })
+    }
+
+    pub fn is_match(&self, s: &str) -> bool {
+        match &self.kind {
+            MatcherKind::Substring(keyword, true) => s.to_lowercase().contains(keyword),
+            MatcherKind::Substring(keyword, false) => s.contains(keyword),
+            MatcherKind::Regex(regex) => regex.is_match(s),
+            MatcherKind::Glob(glob) => glob.is_match(s),
+        }
+    }
+
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub fn mode(&self) -> SearchMode {
+        self.mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matcher() {
+        let matcher = Matcher::new("readme", SearchMode::SmartCase).unwrap();
+        assert!(matcher.is_match("README.md"));
+        let matcher = Matcher::new("README", SearchMode::SmartCase).unwrap();
+        assert!(!matcher.is_match("readme.md"));
+
+        let matcher = Matcher::new(r"\.rs$", SearchMode::Regex).unwrap();
+        assert!(matcher.is_match("main.rs"));
+        assert!(matcher.is_match("MAIN.RS"));
+        assert!(!matcher.is_match("main.rs.bak"));
+        assert!(Matcher::new("(", SearchMode::Regex).is_err());
+
+        let matcher = Matcher::new("*.rs", SearchMode::Glob).unwrap();
+        assert!(matcher.is_match("main.rs"));
+        assert!(!matcher.is_match("main.rs.bak"));
+        let matcher = Matcher::new("?ain.*", SearchMode::Glob).unwrap();
+        assert!(matcher.is_match("Main.toml"));
+    }
+}
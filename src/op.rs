@@ -1,41 +1,88 @@
-use super::state::ItemInfo;
+use super::errors::FxError;
+use super::state::{read_path, ItemInfo};
 
-use log::info;
-use std::path::PathBuf;
+use log::{info, warn};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone)]
+/// File to save the operations, next to the session file.
+pub const OPERATIONS_FILE: &str = ".operations";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Operation {
     pub pos: usize,
     pub op_list: Vec<OpKind>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OpKind {
     Delete(DeletedFiles),
     Put(PutFiles),
     Rename(RenamedFile),
+    BulkRename(RenamedFiles),
+    Move(MovedFiles),
+    Chmod(ChangedModes),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PutFiles {
+    #[serde(with = "item_paths")]
     pub original: Vec<ItemInfo>,
     pub put: Vec<PathBuf>,
     pub dir: PathBuf,
+    /// How the name conflict was resolved for each item, if any.
+    pub policy: Vec<Option<ConflictPolicy>>,
+    /// Backup of the overwritten item for each item, in the trash directory.
+    pub backup: Vec<Option<PathBuf>>,
+}
+
+/// How to resolve the name conflict when putting items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    Overwrite,
+    Skip,
+    Rename,
+    OverwriteIfNewer,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeletedFiles {
     pub trash: Vec<PathBuf>,
+    #[serde(with = "item_paths")]
     pub original: Vec<ItemInfo>,
     pub dir: PathBuf,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenamedFile {
     pub original_name: PathBuf,
     pub new_name: PathBuf,
 }
 
+/// Items renamed at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedFiles {
+    pub original: Vec<PathBuf>,
+    pub renamed: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedFiles {
+    pub original: Vec<PathBuf>,
+    pub moved: Vec<PathBuf>,
+    /// Backup of the overwritten item for each item, in the trash directory.
+    #[serde(default)]
+    pub backup: Vec<Option<PathBuf>>,
+}
+
+/// Modes of the items before and after chmod.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedModes {
+    pub paths: Vec<PathBuf>,
+    pub original: Vec<u32>,
+    pub changed: Vec<u32>,
+}
+
 impl Operation {
     /// Discard undone operations when new one is pushed.
     pub fn branch(&mut self) {
@@ -52,12 +99,73 @@ impl Operation {
         self.op_list.push(op);
         self.pos = 0;
     }
+
+    /// Read the operations saved in the previous session.
+    /// Operations that cannot be undone any more (i.e. the items in the trash are gone),
+    /// and older ones, are discarded.
+    pub fn read(p: &Path) -> Self {
+        let mut operations = match std::fs::read_to_string(p) {
+            Ok(s) => serde_yaml::from_str(&s).unwrap_or_else(|e| {
+                warn!("Cannot read the operations: {}", e);
+                Operation::default()
+            }),
+            Err(_) => Operation::default(),
+        };
+        if operations.pos > operations.op_list.len() {
+            return Operation::default();
+        }
+
+        let undoable = operations.op_list.len() - operations.pos;
+        if let Some(i) = operations.op_list[..undoable]
+            .iter()
+            .rposition(|op| !op.is_undoable())
+        {
+            operations.op_list.drain(..=i);
+        }
+        operations
+    }
+
+    /// Save the operations to the file.
+    pub fn write(&self, p: &Path) -> Result<(), FxError> {
+        let serialized = serde_yaml::to_string(self)?;
+        std::fs::write(p, serialized)?;
+        Ok(())
+    }
+}
+
+impl OpKind {
+    /// Whether the items in the trash referenced by the operation still exist.
+    fn is_undoable(&self) -> bool {
+        let exists = |p: &PathBuf| p.symlink_metadata().is_ok();
+        match self {
+            OpKind::Delete(op) => op.trash.iter().all(exists),
+            OpKind::Put(op) => op.backup.iter().flatten().all(exists),
+            OpKind::Move(op) => op.backup.iter().flatten().all(exists),
+            OpKind::Rename(_) | OpKind::BulkRename(_) | OpKind::Chmod(_) => true,
+        }
+    }
+}
+
+/// Save `ItemInfo` as its path, and read the item again on load.
+mod item_paths {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(items: &[ItemInfo], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(items.iter().map(|item| &item.file_path))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ItemInfo>, D::Error> {
+        let paths: Vec<PathBuf> = Vec::deserialize(deserializer)?;
+        Ok(paths.into_iter().map(read_path).collect())
+    }
 }
 
 fn log(op: &OpKind) {
     match op {
         OpKind::Put(op) => {
-            info!("PUT: {:?}", op.put);
+            info!("PUT: {:?} in {:?}, policy: {:?}", op.put, op.dir, op.policy);
         }
         OpKind::Delete(op) => {
             info!("DELETE: {:?}", item_to_pathvec(&op.original));
@@ -65,6 +173,15 @@ fn log(op: &OpKind) {
         OpKind::Rename(op) => {
             info!("RENAME: {:?} -> {:?}", op.original_name, op.new_name);
         }
+        OpKind::BulkRename(op) => {
+            info!("BULK RENAME: {:?} -> {:?}", op.original, op.renamed);
+        }
+        OpKind::Move(op) => {
+            info!("MOVE: {:?} -> {:?}", op.original, op.moved);
+        }
+        OpKind::Chmod(op) => {
+            info!("CHMOD: {:?}", op.paths);
+        }
     }
 }
 
@@ -87,6 +204,18 @@ pub fn relog(op: &OpKind, undo: bool) {
             result.push_str("RENAME");
             info!("{} {:?} -> {:?}", result, op.original_name, op.new_name);
         }
+        OpKind::BulkRename(op) => {
+            result.push_str("BULK RENAME");
+            info!("{} {:?} -> {:?}", result, op.original, op.renamed);
+        }
+        OpKind::Move(op) => {
+            result.push_str("MOVE");
+            info!("{} {:?} -> {:?}", result, op.original, op.moved);
+        }
+        OpKind::Chmod(op) => {
+            result.push_str("CHMOD");
+            info!("{} {:?}", result, op.paths);
+        }
     }
 }
 
@@ -97,3 +226,51 @@ fn item_to_pathvec(v: &Vec<ItemInfo>) -> Vec<PathBuf> {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_operations() {
+        let base = std::env::temp_dir().join("felix_test_read_operations");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        let trashed = base.join("1671234567_a.txt");
+        std::fs::write(&trashed, "a").unwrap();
+        let delete = |trash: &Path| {
+            OpKind::Delete(DeletedFiles {
+                trash: vec![trash.to_path_buf()],
+                original: vec![read_path(base.join("a.txt"))],
+                dir: base.clone(),
+            })
+        };
+
+        //The operation referring to the missing trash item, and older ones are discarded.
+        let operations = Operation {
+            pos: 0,
+            op_list: vec![
+                delete(&trashed),
+                delete(&base.join("missing")),
+                OpKind::Rename(RenamedFile {
+                    original_name: base.join("b.txt"),
+                    new_name: base.join("c.txt"),
+                }),
+                delete(&trashed),
+            ],
+        };
+        let p = base.join(OPERATIONS_FILE);
+        operations.write(&p).unwrap();
+        let read = Operation::read(&p);
+        assert_eq!(read.op_list.len(), 2);
+        match &read.op_list[1] {
+            OpKind::Delete(op) => {
+                assert_eq!(op.trash, vec![trashed.clone()]);
+                assert_eq!(op.original[0].file_name, "a.txt");
+            }
+            _ => panic!(),
+        }
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,350 @@
+use super::errors::FxError;
+use super::functions::{format_permissions, owner_names, print_warning, read_line, split_str};
+use super::state::{ItemInfo, BEGINNING_ROW};
+use super::term::*;
+
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::style::Stylize;
+use std::io::{Stdout, Write};
+use std::path::Path;
+
+const ROWS: [&str; 3] = ["user", "group", "other"];
+const SPECIAL: [&str; 3] = ["setuid", "setgid", "sticky"];
+
+/// Permissions and ownership edited in the permissions editor.
+/// Toggled bits are applied to each item on top of its own mode,
+/// while the octal input replaces the whole mode of every item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionsEditor {
+    /// Mode of the first item when the editor is opened.
+    base: u32,
+    mode: u32,
+    absolute: bool,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    /// Position in the grid of 3 rows (user, group, other) and 4 columns (r, w, x, special).
+    cursor: usize,
+}
+
+impl PermissionsEditor {
+    pub fn new(mode: u32) -> Self {
+        PermissionsEditor {
+            base: mode & 0o7777,
+            mode: mode & 0o7777,
+            absolute: false,
+            user: None,
+            group: None,
+            cursor: 0,
+        }
+    }
+
+    /// Bit at the position in the grid.
+    fn bit(i: usize) -> u32 {
+        let (row, column) = (i / 4, i % 4);
+        if column < 3 {
+            0o400 >> (row * 3 + column)
+        } else {
+            0o4000 >> row
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.mode ^= Self::bit(self.cursor);
+    }
+
+    /// Set the mode by the octal number such as `755` or `2775`.
+    pub fn set_octal(&mut self, s: &str) -> Result<(), FxError> {
+        match u32::from_str_radix(s.trim(), 8) {
+            Ok(mode) if mode <= 0o7777 => {
+                self.mode = mode;
+                self.absolute = true;
+                Ok(())
+            }
+            _ => Err(FxError::Permissions(format!("Invalid mode: {}", s.trim()))),
+        }
+    }
+
+    /// Whether the mode or the owner is changed.
+    pub fn is_changed(&self) -> bool {
+        self.absolute || self.mode != self.base || self.user.is_some() || self.group.is_some()
+    }
+
+    /// New mode of the item, keeping the file type bits.
+    pub fn apply_to(&self, old: u32) -> u32 {
+        let permissions = if self.absolute {
+            self.mode
+        } else {
+            let on = self.mode & !self.base;
+            let off = self.base & !self.mode;
+            (old & !off) | on
+        };
+        (old & !0o7777) | (permissions & 0o7777)
+    }
+
+    fn draw(&self, items: &[ItemInfo]) -> Result<(), FxError> {
+        clear_all();
+        let (width, _) = terminal_size()?;
+        let width = width as usize;
+        let header = format!(
+            " Permissions of {} item(s) (space: toggle, o: octal, u: user, g: group, Enter: apply, other keys: cancel)",
+            items.len()
+        );
+        move_to(1, 1);
+        print!("{}", split_str(&header, width).bold());
+
+        let first = &items[0];
+        let owner = match (&self.user, &self.group, first.uid, first.gid) {
+            (user, group, Some(uid), Some(gid)) => {
+                let (current_user, current_group) = owner_names(uid, gid);
+                format!(
+                    "{} {}",
+                    user.as_deref().unwrap_or(&current_user),
+                    group.as_deref().unwrap_or(&current_group)
+                )
+            }
+            _ => "".to_owned(),
+        };
+        let mode = self.apply_to(first.permissions.unwrap_or(0));
+        move_to(3, BEGINNING_ROW);
+        print!(
+            "{}",
+            split_str(
+                &format!(
+                    "{} ({:04o}) {}",
+                    format_permissions(mode),
+                    mode & 0o7777,
+                    owner
+                ),
+                width.saturating_sub(3)
+            )
+        );
+        if items.len() > 1 {
+            move_to(3, BEGINNING_ROW + 1);
+            print!(
+                "{}",
+                if self.absolute {
+                    "The mode is set to every item."
+                } else {
+                    "Toggled bits are changed in every item."
+                }
+                .dark_grey()
+            );
+        }
+
+        move_to(3, BEGINNING_ROW + 3);
+        print!("       read  write  execute  special");
+        for (row, name) in ROWS.iter().enumerate() {
+            move_to(3, BEGINNING_ROW + 4 + row as u16);
+            print!("{:<6}", name);
+            for column in 0..4 {
+                let i = row * 4 + column;
+                let mark = if self.mode & Self::bit(i) != 0 {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
+                let cell = if column == 3 {
+                    format!("{} {}", mark, SPECIAL[row])
+                } else {
+                    mark.to_owned()
+                };
+                print!("  ");
+                if i == self.cursor {
+                    print!("{}", cell.negative());
+                } else {
+                    print!("{}", cell);
+                }
+                print!("{}", if column < 3 { "   " } else { "" });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Show the permissions editor of the items.
+/// Returns the editor if Enter is pressed with any change, or `None` if cancelled.
+pub fn edit_permissions(
+    items: &[ItemInfo],
+    mut screen: &Stdout,
+) -> Result<Option<PermissionsEditor>, FxError> {
+    let first = match items.first() {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+    let mut editor = PermissionsEditor::new(first.permissions.unwrap_or(0));
+    loop {
+        editor.draw(items)?;
+        screen.flush()?;
+
+        if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+            match code {
+                KeyCode::Char('h') | KeyCode::Left => {
+                    let column = editor.cursor % 4;
+                    if column > 0 {
+                        editor.cursor -= 1;
+                    }
+                }
+                KeyCode::Char('l') | KeyCode::Right => {
+                    let column = editor.cursor % 4;
+                    if column < 3 {
+                        editor.cursor += 1;
+                    }
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    if editor.cursor >= 4 {
+                        editor.cursor -= 4;
+                    }
+                }
+                KeyCode::Char('j') | KeyCode::Down => {
+                    if editor.cursor + 4 < 12 {
+                        editor.cursor += 4;
+                    }
+                }
+                KeyCode::Char(' ') => editor.toggle(),
+                KeyCode::Char('o') => {
+                    let current = format!("{:o}", editor.mode);
+                    if let Some(octal) = read_line("octal: ", &current)? {
+                        if let Err(e) = editor.set_octal(&octal) {
+                            print_warning(e, BEGINNING_ROW);
+                            crossterm::event::read()?;
+                        }
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if let Some(user) = read_line("user: ", "")? {
+                        if !user.trim().is_empty() {
+                            editor.user = Some(user.trim().to_owned());
+                        }
+                    }
+                }
+                KeyCode::Char('g') => {
+                    if let Some(group) = read_line("group: ", "")? {
+                        if !group.trim().is_empty() {
+                            editor.group = Some(group.trim().to_owned());
+                        }
+                    }
+                }
+                KeyCode::Enter => {
+                    if editor.is_changed() {
+                        return Ok(Some(editor));
+                    }
+                    return Ok(None);
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Set the permission bits of the mode to the item.
+/// Symlinks are not followed, as the mode of the link itself is not used.
+#[cfg(target_family = "unix")]
+pub fn chmod(p: &Path, mode: u32) -> Result<(), FxError> {
+    use std::os::unix::fs::PermissionsExt;
+    if std::fs::symlink_metadata(p)?.file_type().is_symlink() {
+        return Err(FxError::Permissions(format!(
+            "Cannot change the mode of the symlink: {}",
+            p.display()
+        )));
+    }
+    std::fs::set_permissions(p, std::fs::Permissions::from_mode(mode & 0o7777))?;
+    Ok(())
+}
+
+#[cfg(not(target_family = "unix"))]
+pub fn chmod(_p: &Path, _mode: u32) -> Result<(), FxError> {
+    Err(FxError::Permissions(
+        "Changing the mode is not supported on this platform.".to_owned(),
+    ))
+}
+
+/// Change the owner and/or group of the item, by the names or ids.
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+pub fn chown(p: &Path, user: Option<&str>, group: Option<&str>) -> Result<(), FxError> {
+    use nix::unistd::{Gid, Group, Uid, User};
+    let uid = match user {
+        Some(user) => Some(match user.parse::<u32>() {
+            Ok(uid) => Uid::from_raw(uid),
+            Err(_) => {
+                User::from_name(user)?
+                    .ok_or_else(|| FxError::Permissions(format!("No such user: {}", user)))?
+                    .uid
+            }
+        }),
+        None => None,
+    };
+    let gid = match group {
+        Some(group) => Some(match group.parse::<u32>() {
+            Ok(gid) => Gid::from_raw(gid),
+            Err(_) => {
+                Group::from_name(group)?
+                    .ok_or_else(|| FxError::Permissions(format!("No such group: {}", group)))?
+                    .gid
+            }
+        }),
+        None => None,
+    };
+    nix::unistd::fchownat(
+        None,
+        p,
+        uid,
+        gid,
+        nix::unistd::FchownatFlags::NoFollowSymlink,
+    )?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+pub fn chown(_p: &Path, _user: Option<&str>, _group: Option<&str>) -> Result<(), FxError> {
+    Err(FxError::Permissions(
+        "Changing the owner is not supported on this platform.".to_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permissions_editor() {
+        let mut editor = PermissionsEditor::new(0o100644);
+        assert!(!editor.is_changed());
+        //Toggle the execute bit of the user.
+        editor.cursor = 2;
+        editor.toggle();
+        assert_eq!(editor.apply_to(0o100644), 0o100744);
+        //Only the toggled bit is changed in other items.
+        assert_eq!(editor.apply_to(0o40755), 0o40755);
+        assert_eq!(editor.apply_to(0o100600), 0o100700);
+
+        //setgid
+        editor.cursor = 7;
+        editor.toggle();
+        assert_eq!(editor.apply_to(0o40755), 0o42755);
+
+        editor.set_octal("640").unwrap();
+        assert_eq!(editor.apply_to(0o40755), 0o40640);
+        assert!(editor.set_octal("9").is_err());
+        assert!(editor.set_octal("17777").is_err());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_chmod_symlink() {
+        use std::os::unix::fs::PermissionsExt;
+        let base = std::env::temp_dir().join("felix_test_chmod_symlink");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        let file = base.join("a.txt");
+        let link = base.join("link");
+        std::fs::write(&file, "a").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        chmod(&file, 0o600).unwrap();
+        //The target of the symlink is left as it is.
+        assert!(chmod(&link, 0o777).is_err());
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o600);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+use super::errors::FxError;
+use super::state::ItemInfo;
+
+use chrono::prelude::*;
+use regex::Regex;
+
+/// Default format of `{mtime}` in the template.
+const DEFAULT_TIME_FORMAT: &str = "%Y%m%d";
+
+/// How to make new names in `:rename`.
+#[derive(Debug)]
+pub enum Renamer {
+    /// `s/regex/replacement/`, with `g` flag to replace all matches.
+    Substitute {
+        regex: Regex,
+        replacement: String,
+        all: bool,
+    },
+    /// Template such as `photo_{n:03}.{ext}`.
+    Template(Vec<Token>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Token {
+    Text(String),
+    /// Sequence number from 1, with the width and whether to pad with zeros.
+    Number(usize, bool),
+    Name,
+    Stem,
+    Ext,
+    Mtime(String),
+}
+
+impl Renamer {
+    /// Parse the argument of `:rename`: substitution if it starts with `s/`, template otherwise.
+    pub fn new(s: &str) -> Result<Self, FxError> {
+        match s.strip_prefix("s/") {
+            Some(s) => parse_substitute(s),
+            None => Ok(Renamer::Template(parse_template(s)?)),
+        }
+    }
+
+    /// Make new names of the items, in the order of the items.
+    pub fn new_names(&self, items: &[ItemInfo]) -> Vec<String> {
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| match self {
+                Renamer::Substitute {
+                    regex,
+                    replacement,
+                    all,
+                } => {
+                    if *all {
+                        regex
+                            .replace_all(&item.file_name, replacement.as_str())
+                            .to_string()
+                    } else {
+                        regex
+                            .replace(&item.file_name, replacement.as_str())
+                            .to_string()
+                    }
+                }
+                Renamer::Template(tokens) => tokens
+                    .iter()
+                    .map(|token| expand(token, i + 1, item))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+fn parse_substitute(s: &str) -> Result<Renamer, FxError> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('/') => parts.last_mut().unwrap().push('/'),
+                Some(c) => {
+                    parts.last_mut().unwrap().push('\\');
+                    parts.last_mut().unwrap().push(c);
+                }
+                None => parts.last_mut().unwrap().push('\\'),
+            },
+            '/' => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    if parts.len() != 3 || (parts[2] != "g" && !parts[2].is_empty()) {
+        return Err(FxError::Rename(
+            "Substitution must be s/regex/replacement/ or s/regex/replacement/g".to_owned(),
+        ));
+    }
+    let regex = Regex::new(&parts[0]).map_err(|e| FxError::Rename(e.to_string()))?;
+    Ok(Renamer::Substitute {
+        regex,
+        replacement: parts[1].clone(),
+        all: parts[2] == "g",
+    })
+}
+
+fn parse_template(s: &str) -> Result<Vec<Token>, FxError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            //`{{` and `}}` are literal braces.
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(parse_placeholder(&placeholder)?);
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+fn parse_placeholder(s: &str) -> Result<Token, FxError> {
+    let (name, spec) = match s.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (s, None),
+    };
+    match (name, spec) {
+        ("n", None) => Ok(Token::Number(0, false)),
+        ("n", Some(spec)) => match spec.parse::<usize>() {
+            Ok(width) => Ok(Token::Number(width, spec.starts_with('0'))),
+            Err(_) => Err(FxError::Rename(format!("Invalid width: {{{}}}", s))),
+        },
+        ("name", None) => Ok(Token::Name),
+        ("stem", None) => Ok(Token::Stem),
+        ("ext", None) => Ok(Token::Ext),
+        ("mtime", spec) => Ok(Token::Mtime(spec.unwrap_or(DEFAULT_TIME_FORMAT).to_owned())),
+        _ => Err(FxError::Rename(format!("Unknown placeholder: {{{}}}", s))),
+    }
+}
+
+fn expand(token: &Token, n: usize, item: &ItemInfo) -> String {
+    let path = &item.file_path;
+    match token {
+        Token::Text(text) => text.clone(),
+        Token::Number(width, true) => format!("{:0width$}", n, width = width),
+        Token::Number(width, false) => format!("{:width$}", n, width = width),
+        Token::Name => item.file_name.clone(),
+        Token::Stem => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        Token::Ext => path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        Token::Mtime(format) => match std::fs::symlink_metadata(path).and_then(|m| m.modified()) {
+            Ok(time) => {
+                let time: DateTime<Local> = DateTime::from(time);
+                time.format(format).to_string()
+            }
+            Err(_) => String::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn item(name: &str) -> ItemInfo {
+        ItemInfo {
+            file_name: name.to_owned(),
+            file_path: PathBuf::from("/tmp").join(name),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_renamer() {
+        let items = vec![item("IMG_0001.JPG"), item("IMG_0002.JPG"), item("notes")];
+
+        let renamer = Renamer::new("photo_{n:03}.{ext}").unwrap();
+        assert_eq!(
+            renamer.new_names(&items),
+            vec!["photo_001.JPG", "photo_002.JPG", "photo_003."]
+        );
+        let renamer = Renamer::new("{{{stem}}}_{n}").unwrap();
+        assert_eq!(renamer.new_names(&items)[0], "{IMG_0001}_1");
+
+        let renamer = Renamer::new(r"s/IMG_(\d+)/img-$1/").unwrap();
+        assert_eq!(
+            renamer.new_names(&items),
+            vec!["img-0001.JPG", "img-0002.JPG", "notes"]
+        );
+        let renamer = Renamer::new("s/0/o/g").unwrap();
+        assert_eq!(renamer.new_names(&items)[0], "IMG_ooo1.JPG");
+
+        assert!(Renamer::new("{unknown}").is_err());
+        assert!(Renamer::new("s/a/b").is_err());
+        assert!(Renamer::new("s/(/b/").is_err());
+    }
+}
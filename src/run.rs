@@ -1,32 +1,36 @@
 use super::config::{make_config_if_not_exists, CONFIG_FILE};
 use super::errors::FxError;
+use super::finder;
 use super::functions::*;
-use super::layout::Split;
+use super::grep::GrepView;
+use super::job::{JobKind, JOBS_RUNNING_WARNING, JOB_POLL_INTERVAL};
+use super::keymap::*;
+use super::layout::{Layout, Split};
+use super::line_editor::{LineEditor, LineInput};
+use super::matcher::Matcher;
 use super::nums::*;
 use super::op::*;
+use super::permissions;
 use super::session::*;
+use super::shell::{read_command, History, HISTORY_FILE};
 use super::state::*;
 use super::term::*;
+use super::trash::init_xdg_home_trash;
 
 use crossterm::cursor::RestorePosition;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use crossterm::execute;
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use log::{error, info};
-use std::env::set_current_dir;
-use std::fmt::Write as _;
-use std::io::{stdout, Write};
+use std::io::{stdout, Stdout, Write};
 use std::panic;
 use std::path::PathBuf;
-use std::time::Instant;
+use unicode_width::UnicodeWidthStr;
 
 pub const TRASH: &str = "Trash";
 /// Where the item list starts to scroll.
 const SCROLL_POINT: u16 = 3;
 const CLRSCR: &str = "\x1B[2J";
-const INITIAL_POS_RENAME: u16 = 12;
-const INITIAL_POS_SEARCH: usize = 3;
-const INITIAL_POS_SHELL: u16 = 3;
 
 /// Launch the app. If initializing goes wrong, return error.
 pub fn run(arg: PathBuf, log: bool) -> Result<(), FxError> {
@@ -95,13 +99,21 @@ pub fn run(arg: PathBuf, log: bool) -> Result<(), FxError> {
 
     //Initialize app state.
     let mut state = State::new(&config_file_path, &session_file_path)?;
-    state.trash_dir = trash_dir_path;
+    state.operations = Operation::read(&session_file_path.with_file_name(OPERATIONS_FILE));
+    state.shell_history = History::read(&session_file_path.with_file_name(HISTORY_FILE));
+    state.trash_dir = if state.xdg_trash {
+        init_xdg_home_trash()?
+    } else {
+        trash_dir_path
+    };
     state.current_dir = if cfg!(not(windows)) {
         // If executed this on windows, "//?" will be inserted at the beginning of the path.
         arg.canonicalize()?
     } else {
         arg
     };
+    let current_dir = state.current_dir.clone();
+    state.apply_dir_view(&current_dir);
 
     //If the main function causes panic, catch it.
     let result = panic::catch_unwind(|| _run(state, session_file_path));
@@ -148,1460 +160,1281 @@ fn _run(mut state: State, session_path: PathBuf) -> Result<(), FxError> {
     }
     screen.flush()?;
 
+    //Key sequence typed so far, such as `g` of `gg`.
+    let mut keys: Vec<KeyPress> = Vec::new();
+
     'main: loop {
         screen.flush()?;
         let len = state.list.len();
 
-        match event::read()? {
-            Event::Key(KeyEvent {
-                code, modifiers, ..
-            }) => {
-                match modifiers {
-                    KeyModifiers::CONTROL => match code {
-                        KeyCode::Char('r') => {
-                            let op_len = state.operations.op_list.len();
-                            if op_len == 0
-                                || state.operations.pos == 0
-                                || op_len < state.operations.pos
-                            {
-                                print_info("No operations left.", state.layout.y);
-                                continue;
-                            }
-                            if let Some(op) =
-                                state.operations.op_list.get(op_len - state.operations.pos)
-                            {
-                                let op = op.clone();
-                                if let Err(e) = state.redo(&op) {
-                                    print_warning(e, state.layout.y);
-                                    continue;
-                                }
+        //While jobs are running, wake up regularly to show the progress.
+        if state.jobs.is_running() {
+            state.update_jobs()?;
+            screen.flush()?;
+            if !event::poll(JOB_POLL_INTERVAL)? {
+                continue;
+            }
+        }
 
-                                let new_len = state.list.len();
-                                if new_len == 0 {
-                                    state.layout.nums.reset();
-                                    state.move_cursor(BEGINNING_ROW);
-                                } else if state.layout.nums.index > new_len - 1 {
-                                    let new_y = state.layout.y
-                                        - (state.layout.nums.index - (new_len - 1)) as u16;
-                                    state.layout.nums.index = new_len - 1;
-                                    state.move_cursor(new_y)
-                                } else {
-                                    state.move_cursor(state.layout.y);
-                                }
-                            }
-                        }
-                        //Other commands are disabled when Ctrl is pressed.
-                        _ => {
-                            continue;
-                        }
-                    },
-                    KeyModifiers::ALT => match code {
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            if state.layout.preview {
-                                state.scroll_down_preview(state.layout.y);
-                            }
+        match event::read()? {
+            Event::Key(key) => {
+                keys.push(KeyPress::from(key));
+                let action = match state.keymap.lookup(&keys) {
+                    Lookup::Matched(action) => {
+                        if keys.len() > 1 {
+                            hide_cursor();
+                            go_to_and_rest_info();
                         }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            if state.layout.preview {
-                                state.scroll_up_preview(state.layout.y);
-                            }
+                        keys.clear();
+                        action
+                    }
+                    //Wait for the next key, showing the key(s) typed so far.
+                    Lookup::Pending => {
+                        delete_cursor();
+                        go_to_and_rest_info();
+                        print!("{}", display_keys(&keys));
+                        show_cursor();
+                        continue;
+                    }
+                    //If input does not match any of the defined keys, ignore it.
+                    Lookup::NotMatched => {
+                        if keys.len() > 1 {
+                            go_to_and_rest_info();
+                            hide_cursor();
+                            state.move_cursor(state.layout.y);
                         }
-                        //Other commands are disabled when Alt is pressed.
-                        _ => {
+                        keys.clear();
+                        continue;
+                    }
+                };
+
+                match action {
+                    //Go down. If lists exceed max-row, lists "scrolls" before the bottom of the list
+                    Action::Down => {
+                        if len == 0 || state.layout.nums.index == len - 1 {
                             continue;
+                        } else if state.layout.y >= state.layout.terminal_row - 1 - SCROLL_POINT
+                            && len > (state.layout.terminal_row - BEGINNING_ROW) as usize - 1
+                        {
+                            state.layout.nums.go_down();
+                            state.layout.nums.inc_skip();
+                            state.redraw(state.layout.y);
+                        } else {
+                            state.layout.nums.go_down();
+                            state.move_cursor(state.layout.y + 1);
                         }
-                    },
-                    KeyModifiers::NONE | KeyModifiers::SHIFT => {
-                        match code {
-                            //Go up. If lists exceed max-row, lists "scrolls" before the top of the list
-                            KeyCode::Char('j') | KeyCode::Down => {
-                                if len == 0 || state.layout.nums.index == len - 1 {
-                                    continue;
-                                } else if state.layout.y
-                                    >= state.layout.terminal_row - 1 - SCROLL_POINT
-                                    && len
-                                        > (state.layout.terminal_row - BEGINNING_ROW) as usize - 1
-                                {
-                                    state.layout.nums.go_down();
-                                    state.layout.nums.inc_skip();
-                                    state.redraw(state.layout.y);
-                                } else {
-                                    state.layout.nums.go_down();
-                                    state.move_cursor(state.layout.y + 1);
-                                }
-                            }
+                    }
 
-                            //Go down. If lists exceed max-row, lists "scrolls" before the bottom of the list
-                            KeyCode::Char('k') | KeyCode::Up => {
-                                if state.layout.nums.index == 0 {
-                                    continue;
-                                } else if state.layout.y <= BEGINNING_ROW + SCROLL_POINT
-                                    && state.layout.nums.skip != 0
-                                {
-                                    state.layout.nums.go_up();
-                                    state.layout.nums.dec_skip();
-                                    state.redraw(state.layout.y);
-                                } else {
-                                    state.layout.nums.go_up();
-                                    state.move_cursor(state.layout.y - 1);
-                                }
-                            }
+                    //Go up. If lists exceed max-row, lists "scrolls" before the top of the list
+                    Action::Up => {
+                        if state.layout.nums.index == 0 {
+                            continue;
+                        } else if state.layout.y <= BEGINNING_ROW + SCROLL_POINT
+                            && state.layout.nums.skip != 0
+                        {
+                            state.layout.nums.go_up();
+                            state.layout.nums.dec_skip();
+                            state.redraw(state.layout.y);
+                        } else {
+                            state.layout.nums.go_up();
+                            state.move_cursor(state.layout.y - 1);
+                        }
+                    }
 
-                            //Go to top
-                            KeyCode::Char('g') => {
-                                go_to_and_rest_info();
-                                print!("g");
-                                show_cursor();
-                                screen.flush()?;
+                    //Go to top
+                    Action::GoTop => {
+                        state.layout.nums.reset();
+                        state.redraw(BEGINNING_ROW);
+                    }
 
-                                if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                    match code {
-                                        KeyCode::Char('g') => {
-                                            hide_cursor();
-                                            state.layout.nums.reset();
-                                            state.redraw(BEGINNING_ROW);
-                                        }
+                    //Go to bottom
+                    Action::GoBottom => {
+                        if len == 0 {
+                            continue;
+                        }
+                        if len > (state.layout.terminal_row - BEGINNING_ROW) as usize {
+                            state.layout.nums.skip =
+                                (len as u16) + BEGINNING_ROW - state.layout.terminal_row;
+                            state.layout.nums.go_bottom(len - 1);
+                            let cursor_pos = state.layout.terminal_row - 1;
+                            state.redraw(cursor_pos);
+                        } else {
+                            state.layout.nums.go_bottom(len - 1);
+                            state.move_cursor(len as u16 + BEGINNING_ROW - 1);
+                        }
+                    }
 
-                                        _ => {
-                                            hide_cursor();
-                                            clear_current_line();
-                                            state.move_cursor(state.layout.y);
-                                        }
+                    //Open file or change directory
+                    Action::Open => {
+                        let mut dest: Option<PathBuf> = None;
+                        if let Ok(item) = state.get_item() {
+                            match item.file_type {
+                                FileType::File => {
+                                    execute!(screen, EnterAlternateScreen)?;
+                                    if let Err(e) = state.open_file(item) {
+                                        print_warning(e, state.layout.y);
+                                        continue;
                                     }
-                                }
-                            }
-
-                            //Go to bottom
-                            KeyCode::Char('G') => {
-                                if len == 0 {
+                                    execute!(screen, EnterAlternateScreen)?;
+                                    hide_cursor();
+                                    state.reload(state.layout.y)?;
                                     continue;
                                 }
-                                if len > (state.layout.terminal_row - BEGINNING_ROW) as usize {
-                                    state.layout.nums.skip =
-                                        (len as u16) + BEGINNING_ROW - state.layout.terminal_row;
-                                    state.layout.nums.go_bottom(len - 1);
-                                    let cursor_pos = state.layout.terminal_row - 1;
-                                    state.redraw(cursor_pos);
-                                } else {
-                                    state.layout.nums.go_bottom(len - 1);
-                                    state.move_cursor(len as u16 + BEGINNING_ROW - 1);
-                                }
-                            }
-
-                            //Open file or change directory
-                            KeyCode::Char('l') | KeyCode::Enter | KeyCode::Right => {
-                                let mut dest: Option<PathBuf> = None;
-                                if let Ok(item) = state.get_item() {
-                                    match item.file_type {
-                                        FileType::File => {
-                                            execute!(screen, EnterAlternateScreen)?;
-                                            if let Err(e) = state.open_file(item) {
-                                                print_warning(e, state.layout.y);
-                                                continue;
-                                            }
-                                            execute!(screen, EnterAlternateScreen)?;
-                                            hide_cursor();
-                                            state.reload(state.layout.y)?;
+                                FileType::Symlink => match &item.symlink_dir_path {
+                                    Some(true_path) => {
+                                        if true_path.exists() {
+                                            dest = Some(true_path.to_path_buf());
+                                        } else {
+                                            print_warning("Broken link.", state.layout.y);
                                             continue;
                                         }
-                                        FileType::Symlink => match &item.symlink_dir_path {
-                                            Some(true_path) => {
-                                                if true_path.exists() {
-                                                    dest = Some(true_path.to_path_buf());
-                                                } else {
-                                                    print_warning("Broken link.", state.layout.y);
-                                                    continue;
-                                                }
-                                            }
-                                            None => {
-                                                execute!(screen, EnterAlternateScreen)?;
-                                                if let Err(e) = state.open_file(item) {
-                                                    print_warning(e, state.layout.y);
-                                                    continue;
-                                                }
-                                                execute!(screen, EnterAlternateScreen)?;
-                                                hide_cursor();
-                                                state.redraw(state.layout.y);
-                                                continue;
-                                            }
-                                        },
-                                        FileType::Directory => {
-                                            if item.file_path.exists() {
-                                                dest = Some(item.file_path.clone());
-                                            } else {
-                                                print_warning("Invalid directory.", state.layout.y);
-                                                continue;
-                                            }
-                                        }
-                                    }
-                                }
-                                if let Some(dest) = dest {
-                                    if let Err(e) = state.chdir(&dest, Move::Down) {
-                                        print_warning(e, state.layout.y);
                                     }
-                                }
-                            }
-
-                            //Open a file in a new window
-                            //This works only if i) [exec] is set in config file
-                            //and ii) the extension of the item matches the key.
-                            //If not, warning message appears.
-                            KeyCode::Char('o') => {
-                                if let Ok(item) = state.get_item() {
-                                    match item.file_type {
-                                        FileType::File => {
-                                            if let Err(e) = state.open_file_in_new_window() {
-                                                hide_cursor();
-                                                state.redraw(state.layout.y);
-                                                print_warning(e, state.layout.y);
-                                                continue;
-                                            }
-                                            hide_cursor();
-                                            state.redraw(state.layout.y);
-                                            continue;
-                                        }
-                                        _ => {
+                                    None => {
+                                        execute!(screen, EnterAlternateScreen)?;
+                                        if let Err(e) = state.open_file(item) {
+                                            print_warning(e, state.layout.y);
                                             continue;
                                         }
+                                        execute!(screen, EnterAlternateScreen)?;
+                                        hide_cursor();
+                                        state.redraw(state.layout.y);
+                                        continue;
+                                    }
+                                },
+                                FileType::Directory => {
+                                    if item.file_path.exists() {
+                                        dest = Some(item.file_path.clone());
+                                    } else {
+                                        print_warning("Invalid directory.", state.layout.y);
+                                        continue;
                                     }
                                 }
                             }
+                        }
+                        if let Some(dest) = dest {
+                            if let Err(e) = state.chdir(&dest, Move::Down) {
+                                print_warning(e, state.layout.y);
+                            }
+                        }
+                    }
 
-                            //Go to the parent directory if exists.
-                            KeyCode::Char('h') | KeyCode::Left => {
-                                let pre = state.current_dir.clone();
-
-                                match pre.parent() {
-                                    Some(parent_p) => {
-                                        if let Err(e) = state.chdir(parent_p, Move::Up) {
-                                            print_warning(e, state.layout.y);
-                                        }
-                                    }
-                                    None => {
+                    //Open a file in a new window
+                    //This works only if i) [exec] is set in config file
+                    //and ii) the extension of the item matches the key.
+                    //If not, warning message appears.
+                    Action::OpenNewWindow => {
+                        if let Ok(item) = state.get_item() {
+                            match item.file_type {
+                                FileType::File => {
+                                    if let Err(e) = state.open_file_in_new_window() {
+                                        hide_cursor();
+                                        state.redraw(state.layout.y);
+                                        print_warning(e, state.layout.y);
                                         continue;
                                     }
+                                    hide_cursor();
+                                    state.redraw(state.layout.y);
+                                    continue;
+                                }
+                                _ => {
+                                    continue;
                                 }
                             }
+                        }
+                    }
 
-                            //Unpack archive file. Fails if it is not an archive file or any of supported types.
-                            KeyCode::Char('e') => {
-                                print_info("Unpacking...", state.layout.y);
-                                screen.flush()?;
-                                let start = Instant::now();
-                                if let Err(e) = state.unpack() {
-                                    state.reload(state.layout.y)?;
+                    //Go to the parent directory if exists.
+                    Action::Parent => {
+                        let pre = state.current_dir.clone();
+
+                        match pre.parent() {
+                            Some(parent_p) => {
+                                if let Err(e) = state.chdir(parent_p, Move::Up) {
                                     print_warning(e, state.layout.y);
-                                    continue;
                                 }
-                                let duration = duration_to_string(start.elapsed());
-                                state.reload(state.layout.y)?;
-                                print_info(format!("Unpacked. [{}]", duration), state.layout.y);
                             }
+                            None => {
+                                continue;
+                            }
+                        }
+                    }
 
-                            //Jumps to the directory that matches the keyword (zoxide required).
-                            KeyCode::Char('z') => {
-                                delete_cursor();
-                                to_info_line();
-                                clear_current_line();
-                                print!("z");
-                                show_cursor();
+                    //Unpack archive file. Fails if it is not an archive file or any of supported types.
+                    Action::Unpack => {
+                        if let Err(e) = state.unpack() {
+                            print_warning(e, state.layout.y);
+                            continue;
+                        }
+                        print_info("Unpacking...", state.layout.y);
+                    }
 
-                                let mut command: Vec<char> = vec!['z'];
-                                screen.flush()?;
+                    //Jumps to the directory that matches the keyword (zoxide required).
+                    Action::Zoxide => {
+                        delete_cursor();
+                        to_info_line();
+                        clear_current_line();
+                        //Show the key bound to zoxide as the prompt.
+                        let prompt = state
+                            .keymap
+                            .keys_of(Action::Zoxide)
+                            .into_iter()
+                            .next()
+                            .unwrap_or_else(|| "z".to_owned());
+                        print!("{}", prompt);
+                        show_cursor();
+
+                        let mut command: Vec<char> = Vec::new();
+                        screen.flush()?;
+
+                        let initial_pos = 2 + prompt.width() as u16;
+                        let mut current_pos = initial_pos;
+                        'zoxide: loop {
+                            if let Event::Key(KeyEvent { code, .. }) = event::read()? {
+                                match code {
+                                    KeyCode::Esc => {
+                                        go_to_and_rest_info();
+                                        hide_cursor();
+                                        state.move_cursor(state.layout.y);
+                                        break 'zoxide;
+                                    }
 
-                                let initial_pos = 2;
-                                let mut current_pos = 3;
-                                'zoxide: loop {
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            KeyCode::Esc => {
-                                                go_to_and_rest_info();
-                                                hide_cursor();
-                                                state.move_cursor(state.layout.y);
-                                                break 'zoxide;
-                                            }
+                                    KeyCode::Left => {
+                                        if current_pos == initial_pos {
+                                            continue;
+                                        };
+                                        current_pos -= 1;
+                                        move_left(1);
+                                    }
 
-                                            KeyCode::Left => {
-                                                if current_pos == initial_pos {
-                                                    continue;
-                                                };
-                                                current_pos -= 1;
-                                                move_left(1);
-                                            }
+                                    KeyCode::Right => {
+                                        if current_pos as usize
+                                            == command.len() + initial_pos as usize
+                                        {
+                                            continue;
+                                        };
+                                        current_pos += 1;
+                                        move_right(1);
+                                    }
 
-                                            KeyCode::Right => {
-                                                if current_pos as usize
-                                                    == command.len() + initial_pos as usize
-                                                {
-                                                    continue;
-                                                };
-                                                current_pos += 1;
-                                                move_right(1);
-                                            }
+                                    KeyCode::Backspace => {
+                                        if current_pos == initial_pos {
+                                            go_to_and_rest_info();
+                                            hide_cursor();
+                                            state.move_cursor(state.layout.y);
+                                            break 'zoxide;
+                                        };
+                                        command.remove((current_pos - initial_pos - 1).into());
+                                        current_pos -= 1;
+
+                                        clear_current_line();
+                                        to_info_line();
+                                        print!("{}{}", prompt, &command.iter().collect::<String>());
+                                        move_to(current_pos, 2);
+                                    }
 
-                                            KeyCode::Backspace => {
-                                                if current_pos == initial_pos + 1 {
-                                                    go_to_and_rest_info();
-                                                    hide_cursor();
-                                                    state.move_cursor(state.layout.y);
-                                                    break 'zoxide;
-                                                };
-                                                command
-                                                    .remove((current_pos - initial_pos - 1).into());
-                                                current_pos -= 1;
-
-                                                clear_current_line();
-                                                to_info_line();
-                                                print!("{}", &command.iter().collect::<String>(),);
-                                                move_to(current_pos, 2);
+                                    KeyCode::Enter => {
+                                        hide_cursor();
+                                        let command = command.iter().collect::<String>();
+                                        let commands =
+                                            command.split_whitespace().collect::<Vec<&str>>();
+                                        if commands.len() > 1 {
+                                            //Invalid argument.
+                                            print_warning(
+                                                "Invalid argument for zoxide.",
+                                                state.layout.y,
+                                            );
+                                            state.move_cursor(state.layout.y);
+                                            break 'zoxide;
+                                        } else if commands.is_empty() {
+                                            //go to the home directory
+                                            let home_dir = dirs::home_dir().ok_or_else(|| {
+                                                FxError::Dirs("Cannot read home dir.".to_string())
+                                            })?;
+                                            if let Err(e) = state.chdir(&home_dir, Move::Jump) {
+                                                print_warning(e, state.layout.y);
                                             }
-
-                                            KeyCode::Enter => {
-                                                hide_cursor();
-                                                let command = command.iter().collect::<String>();
-                                                let commands = command
-                                                    .split_whitespace()
-                                                    .collect::<Vec<&str>>();
-                                                if commands[0] == "z" {
-                                                    if commands.len() > 2 {
-                                                        //Invalid argument.
-                                                        print_warning(
-                                                            "Invalid argument for zoxide.",
-                                                            state.layout.y,
-                                                        );
-                                                        state.move_cursor(state.layout.y);
+                                            break 'zoxide;
+                                        } else if let Ok(output) =
+                                            std::process::Command::new("zoxide")
+                                                .args(["query", commands[0]])
+                                                .output()
+                                        {
+                                            let output = output.stdout;
+                                            if output.is_empty() {
+                                                print_warning(
+                                                    "Keyword does not match the database.",
+                                                    state.layout.y,
+                                                );
+                                                break 'zoxide;
+                                            } else {
+                                                let target_dir = std::str::from_utf8(&output);
+                                                match target_dir {
+                                                    Err(e) => {
+                                                        print_warning(e, state.layout.y);
                                                         break 'zoxide;
-                                                    } else if commands.len() == 1 {
-                                                        //go to the home directory
-                                                        let home_dir = dirs::home_dir()
-                                                            .ok_or_else(|| {
-                                                                FxError::Dirs(
-                                                                    "Cannot read home dir."
-                                                                        .to_string(),
-                                                                )
-                                                            })?;
+                                                    }
+                                                    Ok(target_dir) => {
+                                                        hide_cursor();
+                                                        let target_path =
+                                                            PathBuf::from(target_dir.trim());
                                                         if let Err(e) =
-                                                            state.chdir(&home_dir, Move::Jump)
+                                                            state.chdir(&target_path, Move::Jump)
                                                         {
                                                             print_warning(e, state.layout.y);
                                                         }
                                                         break 'zoxide;
-                                                    } else if let Ok(output) =
-                                                        std::process::Command::new("zoxide")
-                                                            .args(["query", commands[1]])
-                                                            .output()
-                                                    {
-                                                        let output = output.stdout;
-                                                        if output.is_empty() {
-                                                            print_warning(
-                                                        "Keyword does not match the database.",
-                                                        state.layout.y,
-                                                    );
-                                                            break 'zoxide;
-                                                        } else {
-                                                            let target_dir =
-                                                                std::str::from_utf8(&output);
-                                                            match target_dir {
-                                                                Err(e) => {
-                                                                    print_warning(
-                                                                        e,
-                                                                        state.layout.y,
-                                                                    );
-                                                                    break 'zoxide;
-                                                                }
-                                                                Ok(target_dir) => {
-                                                                    hide_cursor();
-                                                                    state.layout.nums.reset();
-                                                                    let target_path = PathBuf::from(
-                                                                        target_dir.trim(),
-                                                                    );
-                                                                    std::env::set_current_dir(
-                                                                        &target_path,
-                                                                    )?;
-                                                                    state.current_dir =
-                                                                        if cfg!(not(windows)) {
-                                                                            target_path
-                                                                                .canonicalize()?
-                                                                        } else {
-                                                                            target_path
-                                                                        };
-                                                                    state.reload(BEGINNING_ROW)?;
-                                                                    break 'zoxide;
-                                                                }
-                                                            }
-                                                        }
-                                                    } else {
-                                                        print_warning(
-                                                            "zoxide not installed?",
-                                                            state.layout.y,
-                                                        );
-                                                        break 'zoxide;
                                                     }
                                                 }
-                                                //  else {
-                                                //     go_to_and_rest_info();
-                                                //     hide_cursor();
-                                                //     state.move_cursor(state.layout.y);
-                                                //     break 'zoxide;
-                                                // }
-                                            }
-
-                                            KeyCode::Char(c) => {
-                                                command
-                                                    .insert((current_pos - initial_pos).into(), c);
-                                                current_pos += 1;
-                                                clear_current_line();
-                                                to_info_line();
-                                                print!("{}", &command.iter().collect::<String>(),);
-                                                move_to(current_pos, 2);
                                             }
-
-                                            _ => continue,
+                                        } else {
+                                            print_warning("zoxide not installed?", state.layout.y);
+                                            break 'zoxide;
                                         }
-                                        screen.flush()?;
                                     }
-                                }
-                            }
 
-                            //select mode
-                            KeyCode::Char('V') => {
-                                if len == 0 {
-                                    continue;
-                                }
-                                let mut item = state.get_item_mut()?;
-                                item.selected = true;
+                                    KeyCode::Char(c) => {
+                                        command.insert((current_pos - initial_pos).into(), c);
+                                        current_pos += 1;
+                                        clear_current_line();
+                                        to_info_line();
+                                        print!("{}{}", prompt, &command.iter().collect::<String>());
+                                        move_to(current_pos, 2);
+                                    }
 
-                                state.redraw(state.layout.y);
+                                    _ => continue,
+                                }
                                 screen.flush()?;
+                            }
+                        }
+                    }
 
-                                let start_pos = state.layout.nums.index;
-
-                                loop {
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            KeyCode::Char('j') | KeyCode::Down => {
-                                                if len == 0 || state.layout.nums.index == len - 1 {
-                                                    continue;
-                                                } else if state.layout.y
-                                                    >= state.layout.terminal_row - 4
-                                                    && len
-                                                        > (state.layout.terminal_row
-                                                            - BEGINNING_ROW)
-                                                            as usize
-                                                            - 1
-                                                {
-                                                    if state.layout.nums.index >= start_pos {
-                                                        state.layout.nums.go_down();
-                                                        state.layout.nums.inc_skip();
-                                                        let mut item = state.get_item_mut()?;
-                                                        item.selected = true;
-                                                        state.redraw(state.layout.y);
-                                                    } else {
-                                                        let mut item = state.get_item_mut()?;
-                                                        item.selected = false;
-                                                        state.layout.nums.go_down();
-                                                        state.layout.nums.inc_skip();
-                                                        state.redraw(state.layout.y);
-                                                    }
-                                                } else if state.layout.nums.index >= start_pos {
-                                                    state.layout.nums.go_down();
-                                                    let mut item = state.get_item_mut()?;
-                                                    item.selected = true;
-                                                    state.redraw(state.layout.y + 1);
-                                                } else {
-                                                    let mut item = state.get_item_mut()?;
-                                                    item.selected = false;
-                                                    state.layout.nums.go_down();
-                                                    state.redraw(state.layout.y + 1);
-                                                }
-                                            }
-
-                                            KeyCode::Char('k') | KeyCode::Up => {
-                                                if state.layout.nums.index == 0 {
-                                                    continue;
-                                                } else if state.layout.y <= BEGINNING_ROW + 3
-                                                    && state.layout.nums.skip != 0
-                                                {
-                                                    if state.layout.nums.index > start_pos {
-                                                        let mut item = state.get_item_mut()?;
-                                                        item.selected = false;
-                                                        state.layout.nums.go_up();
-                                                        state.layout.nums.dec_skip();
-                                                        state.redraw(state.layout.y);
-                                                    } else {
-                                                        state.layout.nums.go_up();
-                                                        state.layout.nums.dec_skip();
-                                                        let mut item = state.get_item_mut()?;
-                                                        item.selected = true;
-                                                        state.redraw(state.layout.y);
-                                                    }
-                                                } else if state.layout.nums.index > start_pos {
-                                                    let mut item = state.get_item_mut()?;
-                                                    item.selected = false;
-                                                    state.layout.nums.go_up();
-                                                    state.redraw(state.layout.y - 1);
-                                                } else {
-                                                    state.layout.nums.go_up();
-                                                    let mut item = state.get_item_mut()?;
-                                                    item.selected = true;
-                                                    state.redraw(state.layout.y - 1);
-                                                }
-                                            }
-
-                                            KeyCode::Char('g') => {
-                                                if state.layout.nums.index == 0 {
-                                                    continue;
-                                                } else {
-                                                    to_info_line();
-                                                    clear_current_line();
-                                                    print!("g");
-                                                    show_cursor();
-                                                    screen.flush()?;
-
-                                                    if let Event::Key(KeyEvent { code, .. }) =
-                                                        event::read()?
-                                                    {
-                                                        match code {
-                                                            KeyCode::Char('g') => {
-                                                                hide_cursor();
-                                                                state.select_from_top(start_pos);
-                                                                state.layout.nums.reset();
-                                                                state.redraw(BEGINNING_ROW);
-                                                            }
-
-                                                            _ => {
-                                                                go_to_and_rest_info();
-                                                                hide_cursor();
-                                                                state.move_cursor(state.layout.y);
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                            KeyCode::Char('G') => {
-                                                if len
-                                                    > (state.layout.terminal_row - BEGINNING_ROW)
-                                                        as usize
-                                                {
-                                                    state.select_to_bottom(start_pos);
-                                                    state.layout.nums.skip = (len as u16)
-                                                        + BEGINNING_ROW
-                                                        - state.layout.terminal_row;
-                                                    state.layout.nums.go_bottom(len - 1);
-                                                    state.redraw(state.layout.terminal_row - 1);
-                                                } else {
-                                                    state.select_to_bottom(start_pos);
-                                                    state.layout.nums.go_bottom(len - 1);
-                                                    state.redraw(len as u16 + BEGINNING_ROW - 1);
-                                                }
-                                            }
+                    //select mode
+                    Action::Select => {
+                        if len == 0 {
+                            continue;
+                        }
+                        let mut item = state.get_item_mut()?;
+                        item.selected = true;
 
-                                            KeyCode::Char('d') => {
-                                                print_info("DELETE: Processing...", state.layout.y);
-                                                let start = Instant::now();
-                                                screen.flush()?;
-
-                                                state.registered.clear();
-                                                let cloned = state.list.clone();
-                                                let selected: Vec<ItemInfo> = cloned
-                                                    .into_iter()
-                                                    .filter(|item| item.selected)
-                                                    .collect();
-                                                let total = selected.len();
-
-                                                if let Err(e) =
-                                                    state.remove_and_yank(&selected, true)
-                                                {
-                                                    print_warning(e, state.layout.y);
-                                                    break;
-                                                }
+                        state.redraw(state.layout.y);
+                        screen.flush()?;
 
-                                                state.update_list()?;
-                                                let new_len = state.list.len();
-                                                if usize::from(state.layout.nums.skip) >= new_len {
-                                                    state.layout.nums.reset();
-                                                }
-                                                state.clear_and_show_headline();
-                                                state.list_up();
-
-                                                let duration = duration_to_string(start.elapsed());
-                                                let delete_message: String = {
-                                                    if total == 1 {
-                                                        format!("1 item deleted [{}]", duration)
-                                                    } else {
-                                                        let mut count = total.to_string();
-                                                        let _ = write!(
-                                                            count,
-                                                            " items deleted [{}]",
-                                                            duration
-                                                        );
-                                                        count
-                                                    }
-                                                };
-                                                print_info(delete_message, state.layout.y);
-                                                delete_cursor();
-
-                                                if new_len == 0 {
-                                                    state.layout.nums.reset();
-                                                    state.move_cursor(BEGINNING_ROW);
-                                                } else if state.layout.nums.index > new_len - 1 {
-                                                    let mut new_y = state.layout.y
-                                                        - (state.layout.nums.index - (new_len - 1))
-                                                            as u16;
-                                                    if new_y < BEGINNING_ROW {
-                                                        new_y = BEGINNING_ROW;
-                                                    }
-                                                    state.layout.nums.index = new_len - 1;
-                                                    state.move_cursor(new_y);
-                                                    screen.flush()?;
-                                                } else {
-                                                    state.move_cursor(state.layout.y);
-                                                    screen.flush()?;
-                                                }
-                                                break;
-                                            }
+                        let start_pos = state.layout.nums.index;
 
-                                            KeyCode::Char('y') => {
-                                                state.yank_item(true);
-                                                state.reset_selection();
-                                                state.list_up();
-                                                let mut yank_message: String =
-                                                    state.registered.len().to_string();
-                                                yank_message.push_str(" items yanked");
-                                                print_info(yank_message, state.layout.y);
-                                                break;
-                                            }
+                        loop {
+                            if let Event::Key(key) = event::read()? {
+                                keys.push(KeyPress::from(key));
+                                let action = match state.keymap.lookup_select(&keys) {
+                                    Lookup::Matched(action) => {
+                                        if keys.len() > 1 {
+                                            hide_cursor();
+                                            go_to_and_rest_info();
+                                        }
+                                        keys.clear();
+                                        action
+                                    }
+                                    Lookup::Pending => {
+                                        delete_cursor();
+                                        go_to_and_rest_info();
+                                        print!("{}", display_keys(&keys));
+                                        show_cursor();
+                                        screen.flush()?;
+                                        continue;
+                                    }
+                                    Lookup::NotMatched => {
+                                        if keys.len() > 1 {
+                                            go_to_and_rest_info();
+                                            hide_cursor();
+                                            state.move_cursor(state.layout.y);
+                                        }
+                                        keys.clear();
+                                        continue;
+                                    }
+                                };
 
-                                            KeyCode::Esc => {
-                                                state.reset_selection();
+                                match action {
+                                    Action::Down => {
+                                        if len == 0 || state.layout.nums.index == len - 1 {
+                                            continue;
+                                        } else if state.layout.y >= state.layout.terminal_row - 4
+                                            && len
+                                                > (state.layout.terminal_row - BEGINNING_ROW)
+                                                    as usize
+                                                    - 1
+                                        {
+                                            if state.layout.nums.index >= start_pos {
+                                                state.layout.nums.go_down();
+                                                state.layout.nums.inc_skip();
+                                                let mut item = state.get_item_mut()?;
+                                                item.selected = true;
+                                                state.redraw(state.layout.y);
+                                            } else {
+                                                let mut item = state.get_item_mut()?;
+                                                item.selected = false;
+                                                state.layout.nums.go_down();
+                                                state.layout.nums.inc_skip();
                                                 state.redraw(state.layout.y);
-                                                break;
                                             }
+                                        } else if state.layout.nums.index >= start_pos {
+                                            state.layout.nums.go_down();
+                                            let mut item = state.get_item_mut()?;
+                                            item.selected = true;
+                                            state.redraw(state.layout.y + 1);
+                                        } else {
+                                            let mut item = state.get_item_mut()?;
+                                            item.selected = false;
+                                            state.layout.nums.go_down();
+                                            state.redraw(state.layout.y + 1);
+                                        }
+                                    }
 
-                                            _ => {
-                                                continue;
+                                    Action::Up => {
+                                        if state.layout.nums.index == 0 {
+                                            continue;
+                                        } else if state.layout.y <= BEGINNING_ROW + 3
+                                            && state.layout.nums.skip != 0
+                                        {
+                                            if state.layout.nums.index > start_pos {
+                                                let mut item = state.get_item_mut()?;
+                                                item.selected = false;
+                                                state.layout.nums.go_up();
+                                                state.layout.nums.dec_skip();
+                                                state.redraw(state.layout.y);
+                                            } else {
+                                                state.layout.nums.go_up();
+                                                state.layout.nums.dec_skip();
+                                                let mut item = state.get_item_mut()?;
+                                                item.selected = true;
+                                                state.redraw(state.layout.y);
                                             }
+                                        } else if state.layout.nums.index > start_pos {
+                                            let mut item = state.get_item_mut()?;
+                                            item.selected = false;
+                                            state.layout.nums.go_up();
+                                            state.redraw(state.layout.y - 1);
+                                        } else {
+                                            state.layout.nums.go_up();
+                                            let mut item = state.get_item_mut()?;
+                                            item.selected = true;
+                                            state.redraw(state.layout.y - 1);
                                         }
                                     }
-                                    screen.flush()?;
-                                }
-                            }
 
-                            //toggle sortkey
-                            KeyCode::Char('t') => {
-                                match state.layout.sort_by {
-                                    SortKey::Name => {
-                                        state.layout.sort_by = SortKey::Time;
+                                    Action::GoTop => {
+                                        if state.layout.nums.index == 0 {
+                                            continue;
+                                        }
+                                        state.select_from_top(start_pos);
+                                        state.layout.nums.reset();
+                                        state.redraw(BEGINNING_ROW);
                                     }
-                                    SortKey::Time => {
-                                        state.layout.sort_by = SortKey::Name;
+
+                                    Action::GoBottom => {
+                                        if len
+                                            > (state.layout.terminal_row - BEGINNING_ROW) as usize
+                                        {
+                                            state.select_to_bottom(start_pos);
+                                            state.layout.nums.skip = (len as u16) + BEGINNING_ROW
+                                                - state.layout.terminal_row;
+                                            state.layout.nums.go_bottom(len - 1);
+                                            state.redraw(state.layout.terminal_row - 1);
+                                        } else {
+                                            state.select_to_bottom(start_pos);
+                                            state.layout.nums.go_bottom(len - 1);
+                                            state.redraw(len as u16 + BEGINNING_ROW - 1);
+                                        }
                                     }
-                                }
-                                state.layout.nums.reset();
-                                state.reorder(BEGINNING_ROW);
-                            }
 
-                            //Show/hide hidden items.
-                            KeyCode::Backspace => {
-                                match state.layout.show_hidden {
-                                    true => {
-                                        state.list.retain(|x| !x.is_hidden);
-                                        state.layout.show_hidden = false;
+                                    Action::Delete => {
+                                        let selected: Vec<ItemInfo> = state
+                                            .list
+                                            .iter()
+                                            .filter(|item| item.selected)
+                                            .cloned()
+                                            .collect();
+                                        state.reset_selection();
+                                        state.redraw(state.layout.y);
+
+                                        if let Err(e) = state.remove_and_yank(&selected, true) {
+                                            print_warning(e, state.layout.y);
+                                            break;
+                                        }
+                                        print_info("DELETE: Processing...", state.layout.y);
+                                        break;
                                     }
-                                    false => {
-                                        state.layout.show_hidden = true;
-                                        state.update_list()?;
+
+                                    Action::Yank => {
+                                        state.yank_item(true);
+                                        state.reset_selection();
+                                        state.list_up();
+                                        let mut yank_message: String =
+                                            state.registered.len().to_string();
+                                        yank_message.push_str(" items yanked");
+                                        print_info(yank_message, state.layout.y);
+                                        break;
                                     }
-                                }
-                                state.layout.nums.reset();
-                                state.redraw(BEGINNING_ROW);
-                            }
 
-                            //Toggle whether to show preview.
-                            KeyCode::Char('v') => {
-                                state.layout.preview = !state.layout.preview;
-                                if state.layout.preview {
-                                    match state.layout.split {
-                                        Split::Vertical => {
-                                            let new_column = state.layout.terminal_column / 2;
-                                            let new_row = state.layout.terminal_row;
-                                            state.refresh(new_column, new_row, state.layout.y)?;
-                                        }
-                                        Split::Horizontal => {
-                                            let new_row = state.layout.terminal_row / 2;
-                                            let new_column = state.layout.terminal_column;
-                                            state.refresh(new_column, new_row, state.layout.y)?;
+                                    Action::BulkRename => {
+                                        let selected: Vec<ItemInfo> = state
+                                            .list
+                                            .iter()
+                                            .filter(|item| item.selected)
+                                            .cloned()
+                                            .collect();
+                                        state.reset_selection();
+                                        execute!(screen, EnterAlternateScreen)?;
+                                        let result = state.bulk_rename(&selected);
+                                        execute!(screen, EnterAlternateScreen)?;
+                                        hide_cursor();
+                                        state.reload(state.layout.y)?;
+                                        match result {
+                                            Ok(n) => print_info(
+                                                format!("{} item(s) renamed", n),
+                                                state.layout.y,
+                                            ),
+                                            Err(e) => print_warning(e, state.layout.y),
                                         }
+                                        break;
                                     }
-                                } else {
-                                    let (new_column, new_row) = terminal_size()?;
-                                    state.refresh(new_column, new_row, state.layout.y)?;
-                                }
-                            }
 
-                            //Toggle vertical <-> horizontal split.
-                            KeyCode::Char('s') => match state.layout.split {
-                                Split::Vertical => {
-                                    state.layout.split = Split::Horizontal;
-                                    if state.layout.preview {
-                                        let (new_column, mut new_row) = terminal_size()?;
-                                        new_row /= 2;
-                                        state.refresh(new_column, new_row, state.layout.y)?;
+                                    Action::Rename => {
+                                        let selected: Vec<ItemInfo> = state
+                                            .list
+                                            .iter()
+                                            .filter(|item| item.selected)
+                                            .cloned()
+                                            .collect();
+                                        let pattern = read_line("rename: ", "")?;
+                                        state.reset_selection();
+                                        state.list_up();
+                                        let pattern = match pattern {
+                                            Some(pattern) if !pattern.trim().is_empty() => pattern,
+                                            _ => {
+                                                go_to_and_rest_info();
+                                                state.move_cursor(state.layout.y);
+                                                break;
+                                            }
+                                        };
+                                        match state.rename_by_pattern(
+                                            &selected,
+                                            pattern.trim(),
+                                            &screen,
+                                        ) {
+                                            Ok(n) => {
+                                                state.reload(state.layout.y)?;
+                                                print_info(
+                                                    format!("{} item(s) renamed", n),
+                                                    state.layout.y,
+                                                );
+                                            }
+                                            //Keep the preview to show the problem.
+                                            Err(e) => print_warning(e, state.layout.y),
+                                        }
+                                        break;
                                     }
-                                }
-                                Split::Horizontal => {
-                                    state.layout.split = Split::Vertical;
-                                    if state.layout.preview {
-                                        let (mut new_column, new_row) = terminal_size()?;
-                                        new_column /= 2;
-                                        state.refresh(new_column, new_row, state.layout.y)?;
+
+                                    Action::EditPermissions => {
+                                        let selected: Vec<ItemInfo> = state
+                                            .list
+                                            .iter()
+                                            .filter(|item| item.selected)
+                                            .cloned()
+                                            .collect();
+                                        state.reset_selection();
+                                        edit_permissions(&mut state, &screen, &selected)?;
+                                        break;
                                     }
-                                }
-                            },
-
-                            //delete
-                            KeyCode::Char('d') => {
-                                if len == 0 {
-                                    continue;
-                                } else {
-                                    to_info_line();
-                                    clear_current_line();
-                                    print!("d");
-                                    show_cursor();
-                                    screen.flush()?;
-
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            KeyCode::Char('d') => {
-                                                hide_cursor();
-                                                print_info("DELETE: Processing...", state.layout.y);
-                                                screen.flush()?;
-                                                let start = Instant::now();
-
-                                                let target = state.get_item()?.clone();
-                                                let target = vec![target];
-
-                                                if let Err(e) = state.remove_and_yank(&target, true)
-                                                {
-                                                    print_warning(e, state.layout.y);
-                                                    continue;
-                                                }
 
-                                                state.clear_and_show_headline();
-                                                state.update_list()?;
-                                                state.list_up();
-                                                state.layout.y = if state.list.is_empty() {
-                                                    BEGINNING_ROW
-                                                } else if state.layout.nums.index == len - 1 {
-                                                    state.layout.nums.go_up();
-                                                    state.layout.y - 1
-                                                } else {
-                                                    state.layout.y
-                                                };
-                                                let duration = duration_to_string(start.elapsed());
-                                                print_info(
-                                                    format!("1 item deleted [{}]", duration),
-                                                    state.layout.y,
-                                                );
-                                                state.move_cursor(state.layout.y);
-                                            }
-                                            _ => {
+                                    //Run a command with the selected items as `%s`.
+                                    Action::Shell => {
+                                        delete_cursor();
+                                        show_cursor();
+                                        let quit = match read_command(
+                                            &mut state.shell_history,
+                                            &state.current_dir,
+                                            &state.custom_commands,
+                                        )? {
+                                            Some(line) => run_command(&mut state, &screen, &line)?,
+                                            None => {
                                                 go_to_and_rest_info();
                                                 hide_cursor();
-                                                state.move_cursor(state.layout.y);
+                                                false
                                             }
+                                        };
+                                        if quit {
+                                            break 'main;
                                         }
+                                        state.reset_selection();
+                                        state.list_up();
+                                        state.move_cursor(state.layout.y);
+                                        break;
                                     }
-                                }
-                            }
 
-                            //yank
-                            KeyCode::Char('y') => {
-                                if len == 0 {
-                                    continue;
-                                }
-                                to_info_line();
-                                clear_current_line();
-                                print!("y");
-                                show_cursor();
-                                screen.flush()?;
+                                    Action::Cut => {
+                                        state.cut_item(true);
+                                        state.reset_selection();
+                                        state.list_up();
+                                        let mut cut_message: String =
+                                            state.registered.len().to_string();
+                                        cut_message.push_str(" items cut");
+                                        print_info(cut_message, state.layout.y);
+                                        break;
+                                    }
 
-                                if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                    match code {
-                                        KeyCode::Char('y') => {
-                                            state.yank_item(false);
-                                            go_to_and_rest_info();
-                                            hide_cursor();
-                                            print_info("1 item yanked", state.layout.y);
-                                        }
+                                    Action::Select => {
+                                        state.reset_selection();
+                                        state.redraw(state.layout.y);
+                                        break;
+                                    }
 
-                                        _ => {
-                                            go_to_and_rest_info();
-                                            hide_cursor();
-                                            state.move_cursor(state.layout.y);
-                                        }
+                                    _ => {
+                                        continue;
                                     }
                                 }
                             }
+                            screen.flush()?;
+                        }
+                    }
 
-                            //put
-                            KeyCode::Char('p') => {
-                                if state.registered.is_empty() {
-                                    continue;
-                                }
-                                print_info("PUT: Processing...", state.layout.y);
-                                screen.flush()?;
-                                let start = Instant::now();
+                    //Switch to the next sort key.
+                    Action::ToggleSort => {
+                        state.layout.sort_by = state.layout.sort_by.next();
+                        state.layout.nums.reset();
+                        state.reorder(BEGINNING_ROW);
+                        print_info(sort_message(&state.layout), state.layout.y);
+                    }
 
-                                let targets = state.registered.clone();
-                                if let Err(e) = state.put_items(&targets, None) {
-                                    print_warning(e, state.layout.y);
-                                    continue;
-                                }
+                    //Reverse the sort order.
+                    Action::ReverseSort => {
+                        state.layout.sort_reverse = !state.layout.sort_reverse;
+                        state.layout.nums.reset();
+                        state.reorder(BEGINNING_ROW);
+                        print_info(sort_message(&state.layout), state.layout.y);
+                    }
 
-                                state.reload(state.layout.y)?;
+                    //List directories first, or mix them with files.
+                    Action::ToggleDirsFirst => {
+                        state.layout.dirs_first = !state.layout.dirs_first;
+                        state.layout.nums.reset();
+                        state.reorder(BEGINNING_ROW);
+                        print_info(sort_message(&state.layout), state.layout.y);
+                    }
 
-                                let duration = duration_to_string(start.elapsed());
-                                let registered_len = state.registered.len();
-                                let mut put_message = registered_len.to_string();
-                                if registered_len == 1 {
-                                    let _ = write!(put_message, " item inserted [{}]", duration);
-                                } else {
-                                    let _ = write!(put_message, " items inserted [{}]", duration);
-                                }
-                                print_info(put_message, state.layout.y);
+                    //Show/hide hidden items.
+                    Action::ToggleHidden => {
+                        match state.layout.show_hidden {
+                            true => {
+                                state.list.retain(|x| !x.is_hidden);
+                                state.layout.show_hidden = false;
                             }
+                            false => {
+                                state.layout.show_hidden = true;
+                                state.update_list()?;
+                            }
+                        }
+                        state.layout.nums.reset();
+                        state.redraw(BEGINNING_ROW);
+                    }
 
-                            //rename
-                            KeyCode::Char('c') => {
-                                if len == 0 {
-                                    continue;
+                    //Toggle whether to show preview.
+                    Action::TogglePreview => {
+                        if state.pane.is_some() {
+                            print_warning(
+                                "Preview is not available in the dual-pane mode.",
+                                state.layout.y,
+                            );
+                            continue;
+                        }
+                        state.layout.preview = !state.layout.preview;
+                        if state.layout.preview {
+                            match state.layout.split {
+                                Split::Vertical => {
+                                    let new_column = state.layout.terminal_column / 2;
+                                    let new_row = state.layout.terminal_row;
+                                    state.refresh(new_column, new_row, state.layout.y)?;
                                 }
-                                let item = state.get_item()?.clone();
-                                if !is_editable(&item.file_name) {
-                                    print_warning(
-                                        "Item name cannot be renamed due to the character type.",
-                                        state.layout.y,
-                                    );
-                                    continue;
+                                Split::Horizontal => {
+                                    let new_row = state.layout.terminal_row / 2;
+                                    let new_column = state.layout.terminal_column;
+                                    state.refresh(new_column, new_row, state.layout.y)?;
                                 }
+                            }
+                        } else {
+                            let (new_column, new_row) = terminal_size()?;
+                            state.refresh(new_column, new_row, state.layout.y)?;
+                        }
+                    }
 
-                                show_cursor();
-                                let mut rename = item.file_name.chars().collect::<Vec<char>>();
-                                to_info_line();
-                                clear_current_line();
-                                print!("New name: {}", &rename.iter().collect::<String>(),);
-                                screen.flush()?;
-
-                                let mut current_pos: u16 = 12 + item.file_name.len() as u16;
-                                loop {
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            //rename item
-                                            KeyCode::Enter => {
-                                                let rename = rename.iter().collect::<String>();
-                                                let mut to = state.current_dir.clone();
-                                                to.push(rename);
-                                                if let Err(e) =
-                                                    std::fs::rename(&item.file_path, &to)
-                                                {
-                                                    hide_cursor();
-                                                    print_warning(e, state.layout.y);
-                                                    break;
-                                                }
-
-                                                state.operations.branch();
-                                                state.operations.push(OpKind::Rename(
-                                                    RenamedFile {
-                                                        original_name: item.file_path.clone(),
-                                                        new_name: to,
-                                                    },
-                                                ));
-
-                                                hide_cursor();
-                                                state.reload(state.layout.y)?;
-                                                break;
-                                            }
+                    //Toggle vertical <-> horizontal split.
+                    Action::ToggleSplit => match state.layout.split {
+                        Split::Vertical => {
+                            state.layout.split = Split::Horizontal;
+                            if state.layout.preview {
+                                let (new_column, mut new_row) = terminal_size()?;
+                                new_row /= 2;
+                                state.refresh(new_column, new_row, state.layout.y)?;
+                            }
+                        }
+                        Split::Horizontal => {
+                            state.layout.split = Split::Vertical;
+                            if state.layout.preview {
+                                let (mut new_column, new_row) = terminal_size()?;
+                                new_column /= 2;
+                                state.refresh(new_column, new_row, state.layout.y)?;
+                            }
+                        }
+                    },
 
-                                            KeyCode::Esc => {
-                                                go_to_and_rest_info();
-                                                hide_cursor();
-                                                state.move_cursor(state.layout.y);
-                                                break;
-                                            }
+                    //delete
+                    Action::Delete => {
+                        if len == 0 {
+                            continue;
+                        }
+                        let target = state.get_item()?.clone();
+                        let target = vec![target];
 
-                                            KeyCode::Left => {
-                                                if current_pos == INITIAL_POS_RENAME {
-                                                    continue;
-                                                };
-                                                current_pos -= 1;
-                                                move_left(1);
-                                            }
+                        if let Err(e) = state.remove_and_yank(&target, true) {
+                            print_warning(e, state.layout.y);
+                            continue;
+                        }
+                        print_info("DELETE: Processing...", state.layout.y);
+                    }
 
-                                            KeyCode::Right => {
-                                                if current_pos as usize
-                                                    == rename.len() + INITIAL_POS_RENAME as usize
-                                                {
-                                                    continue;
-                                                };
-                                                current_pos += 1;
-                                                move_right(1);
-                                            }
+                    //yank
+                    Action::Yank => {
+                        if len == 0 {
+                            continue;
+                        }
+                        state.yank_item(false);
+                        print_info("1 item yanked", state.layout.y);
+                    }
 
-                                            KeyCode::Char(c) => {
-                                                rename.insert(
-                                                    (current_pos - INITIAL_POS_RENAME).into(),
-                                                    c,
-                                                );
-                                                current_pos += 1;
+                    //cut
+                    Action::Cut => {
+                        if len == 0 {
+                            continue;
+                        }
+                        state.cut_item(false);
+                        print_info("1 item cut", state.layout.y);
+                    }
 
-                                                to_info_line();
-                                                clear_current_line();
-                                                print!(
-                                                    "New name: {}",
-                                                    &rename.iter().collect::<String>(),
-                                                );
-                                                move_to(current_pos, 2);
-                                            }
+                    //put
+                    Action::Put => {
+                        if state.registered.is_empty() {
+                            continue;
+                        }
+                        print_info("PUT: Processing...", state.layout.y);
+                        screen.flush()?;
+
+                        //In the dual-pane mode, put items in the other pane.
+                        let targets = state.registered.clone();
+                        let target_dir =
+                            state.pane.as_ref().map(|pane| pane.tab.current_dir.clone());
+
+                        //Cut items are moved only once.
+                        if state.cut {
+                            if let Err(e) = state.move_items(&targets, target_dir) {
+                                print_warning(e, state.layout.y);
+                                continue;
+                            }
+                            state.registered.clear();
+                            state.cut = false;
+                            continue;
+                        }
 
-                                            KeyCode::Backspace => {
-                                                if current_pos == INITIAL_POS_RENAME {
-                                                    continue;
-                                                };
-                                                rename.remove(
-                                                    (current_pos - INITIAL_POS_RENAME - 1).into(),
-                                                );
-                                                current_pos -= 1;
+                        if let Err(e) = state.put_items(&targets, target_dir, true) {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
 
-                                                to_info_line();
-                                                clear_current_line();
-                                                print!(
-                                                    "New name: {}",
-                                                    &rename.iter().collect::<String>(),
-                                                );
-                                                move_to(current_pos, 2);
-                                            }
+                    //bulk rename in the editor
+                    Action::BulkRename => {
+                        if state.list.is_empty() {
+                            continue;
+                        }
+                        //The selected items, or the current item.
+                        let mut targets: Vec<ItemInfo> = state
+                            .list
+                            .iter()
+                            .filter(|item| item.selected)
+                            .cloned()
+                            .collect();
+                        if targets.is_empty() {
+                            targets.push(state.get_item()?.clone());
+                        }
+                        execute!(screen, EnterAlternateScreen)?;
+                        let result = state.bulk_rename(&targets);
+                        execute!(screen, EnterAlternateScreen)?;
+                        hide_cursor();
+                        state.reload(state.layout.y)?;
+                        match result {
+                            Ok(n) => print_info(format!("{} item(s) renamed", n), state.layout.y),
+                            Err(e) => print_warning(e, state.layout.y),
+                        }
+                    }
 
-                                            _ => continue,
-                                        }
-                                        screen.flush()?;
-                                    }
-                                }
-                            }
+                    //Edit the permissions and owner of the item.
+                    Action::EditPermissions => {
+                        if len == 0 {
+                            continue;
+                        }
+                        let item = state.get_item()?.clone();
+                        edit_permissions(&mut state, &screen, &[item])?;
+                    }
 
-                            //search mode
-                            KeyCode::Char('/') => {
-                                if len == 0 {
+                    //rename
+                    Action::Rename => {
+                        if len == 0 {
+                            continue;
+                        }
+                        let item = state.get_item()?.clone();
+                        match read_line("New name: ", &item.file_name)? {
+                            //rename item
+                            Some(rename) => {
+                                let mut to = state.current_dir.clone();
+                                to.push(rename);
+                                if let Err(e) = std::fs::rename(&item.file_path, &to) {
+                                    print_warning(e, state.layout.y);
                                     continue;
                                 }
-                                delete_cursor();
-                                show_cursor();
-                                to_info_line();
-                                clear_current_line();
-                                print!("/");
-                                screen.flush()?;
 
-                                let original_nums = state.layout.nums;
-                                let original_y = state.layout.y;
-                                let mut keyword: Vec<char> = Vec::new();
+                                state.operations.branch();
+                                state.operations.push(OpKind::Rename(RenamedFile {
+                                    original_name: item.file_path.clone(),
+                                    new_name: to,
+                                }));
 
-                                let mut current_pos = INITIAL_POS_SEARCH;
-                                loop {
-                                    let keyword_len = keyword.len();
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            KeyCode::Enter => {
-                                                go_to_and_rest_info();
-                                                state.keyword = Some(keyword.iter().collect());
+                                state.reload(state.layout.y)?;
+                            }
+                            None => {
+                                go_to_and_rest_info();
+                                state.move_cursor(state.layout.y);
+                            }
+                        }
+                    }
+
+                    //search mode
+                    Action::Search => {
+                        if len == 0 {
+                            continue;
+                        }
+                        delete_cursor();
+                        show_cursor();
+
+                        let original_nums = state.layout.nums;
+                        let original_y = state.layout.y;
+                        let mut mode = state.search_mode;
+                        let mut editor = LineEditor::new(mode.prompt(), "");
+                        loop {
+                            editor.draw();
+                            screen.flush()?;
+                            if let Event::Key(key) = event::read()? {
+                                //Tab switches the search mode, keeping the keyword.
+                                let input = if key.code == KeyCode::Tab {
+                                    mode = mode.next();
+                                    state.search_mode = mode;
+                                    editor = LineEditor::new(mode.prompt(), editor.text());
+                                    LineInput::Changed
+                                } else {
+                                    editor.handle(key)
+                                };
+                                match input {
+                                    LineInput::Enter => {
+                                        go_to_and_rest_info();
+                                        match Matcher::new(editor.text(), mode) {
+                                            Ok(matcher) => {
+                                                state.keyword = Some(matcher);
                                                 state.move_cursor(state.layout.y);
-                                                break;
                                             }
-
-                                            KeyCode::Esc => {
-                                                hide_cursor();
+                                            Err(e) => {
+                                                state.keyword = None;
                                                 state.redraw(state.layout.y);
-                                                break;
-                                            }
-
-                                            KeyCode::Left => {
-                                                if current_pos == INITIAL_POS_SEARCH {
-                                                    continue;
-                                                }
-                                                current_pos -= 1;
-                                                move_left(1);
-                                            }
-
-                                            KeyCode::Right => {
-                                                if current_pos == keyword_len + INITIAL_POS_SEARCH {
-                                                    continue;
-                                                }
-                                                current_pos += 1;
-                                                move_right(1);
-                                            }
-
-                                            KeyCode::Backspace => {
-                                                if current_pos == INITIAL_POS_SEARCH {
-                                                    hide_cursor();
-                                                    state.redraw(state.layout.y);
-                                                    break;
-                                                } else {
-                                                    keyword.remove(
-                                                        current_pos - INITIAL_POS_SEARCH - 1,
-                                                    );
-                                                    current_pos -= 1;
-
-                                                    let key = &keyword.iter().collect::<String>();
-
-                                                    let target = state
-                                                        .list
-                                                        .iter()
-                                                        .position(|x| x.file_name.contains(key));
-
-                                                    match target {
-                                                        Some(i) => {
-                                                            state.layout.nums.skip = i as u16;
-                                                            state.layout.nums.index = i;
-                                                            state.highlight_matches(key);
-                                                            state.redraw(BEGINNING_ROW);
-                                                        }
-                                                        None => {
-                                                            state.highlight_matches(key);
-                                                            state.layout.nums = original_nums;
-                                                            state.layout.y = original_y;
-                                                            state.redraw(state.layout.y);
-                                                        }
-                                                    }
-                                                    to_info_line();
-                                                    clear_current_line();
-                                                    print!("/{}", key.clone());
-                                                    move_to(current_pos as u16, 2);
-                                                }
+                                                print_warning(e, state.layout.y);
                                             }
+                                        }
+                                        break;
+                                    }
 
-                                            KeyCode::Char(c) => {
-                                                keyword.insert(current_pos - INITIAL_POS_SEARCH, c);
-                                                current_pos += 1;
-
-                                                let key = &keyword.iter().collect::<String>();
+                                    LineInput::Cancel => {
+                                        hide_cursor();
+                                        state.redraw(state.layout.y);
+                                        break;
+                                    }
 
-                                                let target = state
-                                                    .list
-                                                    .iter()
-                                                    .position(|x| x.file_name.contains(key));
+                                    //Backspace at the beginning exits the search mode.
+                                    LineInput::Unhandled if key.code == KeyCode::Backspace => {
+                                        hide_cursor();
+                                        state.redraw(state.layout.y);
+                                        break;
+                                    }
 
-                                                match target {
-                                                    Some(i) => {
-                                                        state.layout.nums.skip = i as u16;
-                                                        state.layout.nums.index = i;
-                                                        state.highlight_matches(key);
-                                                        state.redraw(BEGINNING_ROW);
-                                                    }
-                                                    None => {
-                                                        state.highlight_matches(key);
-                                                        state.layout.nums = original_nums;
-                                                        state.layout.y = original_y;
-                                                        state.redraw(state.layout.y);
-                                                    }
-                                                }
+                                    LineInput::Changed => {
+                                        //Incomplete regex or glob matches nothing while typing.
+                                        let matcher = Matcher::new(editor.text(), mode).ok();
+                                        let target = matcher.as_ref().and_then(|matcher| {
+                                            state
+                                                .list
+                                                .iter()
+                                                .position(|x| matcher.is_match(&x.file_name))
+                                        });
+
+                                        match &matcher {
+                                            Some(matcher) => state.highlight_matches(matcher),
+                                            None => state
+                                                .list
+                                                .iter_mut()
+                                                .for_each(|item| item.matches = false),
+                                        }
 
-                                                to_info_line();
-                                                clear_current_line();
-                                                print!("/{}", key.clone());
-                                                move_to(current_pos as u16, 2);
+                                        match target {
+                                            Some(i) => {
+                                                state.layout.nums.skip = i as u16;
+                                                state.layout.nums.index = i;
+                                                state.redraw(BEGINNING_ROW);
+                                            }
+                                            None => {
+                                                state.layout.nums = original_nums;
+                                                state.layout.y = original_y;
+                                                state.redraw(state.layout.y);
                                             }
-
-                                            _ => continue,
                                         }
-                                        screen.flush()?;
                                     }
+
+                                    _ => continue,
                                 }
-                                hide_cursor();
                             }
+                        }
+                        hide_cursor();
+                    }
 
-                            //Search forward.
-                            KeyCode::Char('n') => match &state.keyword {
-                                None => {
-                                    continue;
-                                }
-                                Some(keyword) => {
-                                    let next = state
-                                        .list
-                                        .iter()
-                                        .skip(state.layout.nums.index + 1)
-                                        .position(|x| x.file_name.contains(keyword));
-                                    match next {
-                                        None => {
-                                            continue;
-                                        }
-                                        Some(i) => {
-                                            let i = i + state.layout.nums.index + 1;
-                                            state.layout.nums.skip = i as u16;
-                                            state.layout.nums.index = i;
-                                            state.redraw(BEGINNING_ROW);
-                                        }
+                    //filter mode
+                    Action::Filter => {
+                        delete_cursor();
+                        show_cursor();
+
+                        let original = state.filter.take();
+                        let original_nums = state.layout.nums;
+                        let original_y = state.layout.y;
+                        state.update_list()?;
+                        let all = std::mem::take(&mut state.list);
+                        let mut mode = original
+                            .as_ref()
+                            .map_or(state.search_mode, |filter| filter.mode());
+                        let mut editor = LineEditor::new(
+                            &format!("filter {}", mode.prompt()),
+                            original.as_ref().map_or("", |filter| filter.keyword()),
+                        );
+                        let mut input = LineInput::Changed;
+                        loop {
+                            match input {
+                                LineInput::Enter => {
+                                    if let Err(e) = Matcher::new(editor.text(), mode) {
+                                        state.filter = original;
+                                        state.apply_filter(&all);
+                                        state.layout.nums = original_nums;
+                                        state.layout.y = original_y;
+                                        state.redraw(state.layout.y);
+                                        print_warning(e, state.layout.y);
+                                    } else {
+                                        state.redraw(state.layout.y);
                                     }
+                                    break;
                                 }
-                            },
 
-                            //Search backward.
-                            KeyCode::Char('N') => match &state.keyword {
-                                None => {
-                                    continue;
-                                }
-                                Some(keyword) => {
-                                    let previous = state
-                                        .list
-                                        .iter()
-                                        .take(state.layout.nums.index)
-                                        .rposition(|x| x.file_name.contains(keyword));
-                                    match previous {
-                                        None => {
-                                            continue;
-                                        }
-                                        Some(i) => {
-                                            state.layout.nums.skip = i as u16;
-                                            state.layout.nums.index = i;
-                                            state.redraw(BEGINNING_ROW);
-                                        }
-                                    }
+                                LineInput::Cancel => {
+                                    state.filter = original;
+                                    state.apply_filter(&all);
+                                    state.layout.nums = original_nums;
+                                    state.layout.y = original_y;
+                                    state.redraw(state.layout.y);
+                                    break;
                                 }
-                            },
-
-                            //shell mode
-                            KeyCode::Char(':') => {
-                                delete_cursor();
-                                to_info_line();
-                                clear_current_line();
-                                print!(":");
-                                show_cursor();
-                                screen.flush()?;
-
-                                let mut command: Vec<char> = Vec::new();
-
-                                let mut current_pos = INITIAL_POS_SHELL;
-                                'command: loop {
-                                    if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                        match code {
-                                            KeyCode::Esc => {
-                                                go_to_and_rest_info();
-                                                hide_cursor();
-                                                state.move_cursor(state.layout.y);
-                                                break 'command;
-                                            }
-
-                                            KeyCode::Left => {
-                                                if current_pos == INITIAL_POS_SHELL {
-                                                    continue;
-                                                };
-                                                current_pos -= 1;
-                                                move_left(1);
-                                            }
-
-                                            KeyCode::Right => {
-                                                if current_pos as usize
-                                                    == command.len() + INITIAL_POS_SHELL as usize
-                                                {
-                                                    continue;
-                                                };
-                                                current_pos += 1;
-                                                move_right(1);
-                                            }
 
-                                            KeyCode::Backspace => {
-                                                if current_pos == INITIAL_POS_SHELL {
-                                                    go_to_and_rest_info();
-                                                    hide_cursor();
-                                                    state.move_cursor(state.layout.y);
-                                                    break 'command;
-                                                } else {
-                                                    command.remove(
-                                                        (current_pos - INITIAL_POS_SHELL - 1)
-                                                            .into(),
-                                                    );
-                                                    current_pos -= 1;
-
-                                                    clear_current_line();
-                                                    to_info_line();
-                                                    print!(
-                                                        ":{}",
-                                                        &command.iter().collect::<String>()
-                                                    );
-                                                    move_to(current_pos, 2);
-                                                }
+                                //Empty filter shows all the items, and incomplete regex or glob shows nothing.
+                                LineInput::Changed => {
+                                    if editor.text().is_empty() {
+                                        state.filter = None;
+                                        state.apply_filter(&all);
+                                    } else {
+                                        match Matcher::new(editor.text(), mode) {
+                                            Ok(matcher) => {
+                                                state.filter = Some(matcher);
+                                                state.apply_filter(&all);
                                             }
-
-                                            KeyCode::Char(c) => {
-                                                command.insert(
-                                                    (current_pos - INITIAL_POS_SHELL).into(),
-                                                    c,
-                                                );
-                                                current_pos += 1;
-                                                clear_current_line();
-                                                to_info_line();
-                                                print!(":{}", &command.iter().collect::<String>(),);
-                                                move_to(current_pos, 2);
+                                            Err(_) => {
+                                                state.filter = None;
+                                                state.list.clear();
                                             }
+                                        }
+                                    }
+                                    state.layout.nums.reset();
+                                    state.redraw(BEGINNING_ROW);
+                                }
 
-                                            KeyCode::Enter => {
-                                                hide_cursor();
-                                                //Set the command and argument(s).
-                                                let commands: String = command.iter().collect();
-                                                let commands: Vec<&str> =
-                                                    commands.split_whitespace().collect();
-                                                if commands.is_empty() {
-                                                    go_to_and_rest_info();
-                                                    state.move_cursor(state.layout.y);
-                                                    break;
-                                                }
-                                                let command = commands[0];
-
-                                                if commands.len() == 1 {
-                                                    if command == "q" {
-                                                        //quit
-                                                        break 'main;
-                                                    } else if command == "cd" || command == "z" {
-                                                        //go to the home directory
-                                                        let home_dir = dirs::home_dir()
-                                                            .ok_or_else(|| {
-                                                                FxError::Dirs(
-                                                                    "Cannot read home dir."
-                                                                        .to_string(),
-                                                                )
-                                                            })?;
-                                                        if let Err(e) =
-                                                            state.chdir(&home_dir, Move::Jump)
-                                                        {
-                                                            print_warning(e, state.layout.y);
-                                                        }
-                                                        break 'command;
-                                                    } else if command == "e" {
-                                                        //reload current dir
-                                                        state.keyword = None;
-                                                        state.layout.nums.reset();
-                                                        state.reload(BEGINNING_ROW)?;
-                                                        break 'command;
-                                                    } else if command == "h" {
-                                                        //show help
-                                                        state.show_help(&screen)?;
-                                                        state.redraw(state.layout.y);
-                                                        break 'command;
-                                                    } else if command == "trash" {
-                                                        //move to trash dir
-                                                        state.layout.nums.reset();
-                                                        if let Err(e) = state.chdir(
-                                                            &(state.trash_dir.clone()),
-                                                            Move::Jump,
-                                                        ) {
-                                                            print_warning(e, state.layout.y);
-                                                        }
-                                                        break 'command;
-                                                    } else if command == "empty" {
-                                                        //empty the trash dir
-                                                        state.empty_trash(&screen)?;
-                                                        break 'command;
-                                                    }
-                                                }
-
-                                                //zoxide jump
-                                                if command == "z" && commands.len() == 2 {
-                                                    //Change directory using zoxide
-                                                    if let Ok(output) =
-                                                        std::process::Command::new("zoxide")
-                                                            .args(["query", commands[1].trim()])
-                                                            .output()
-                                                    {
-                                                        let output = output.stdout;
-                                                        if output.is_empty() {
-                                                            print_warning(
-                                                        "Keyword does not match the database.",
-                                                        state.layout.y,
-                                                    );
-                                                            break 'command;
-                                                        } else {
-                                                            let target_dir =
-                                                                std::str::from_utf8(&output);
-                                                            match target_dir {
-                                                                Err(e) => {
-                                                                    print_warning(
-                                                                        e,
-                                                                        state.layout.y,
-                                                                    );
-                                                                    break 'command;
-                                                                }
-                                                                Ok(target_dir) => {
-                                                                    state.layout.nums.reset();
-                                                                    let target_path = PathBuf::from(
-                                                                        target_dir.trim(),
-                                                                    );
-                                                                    if let Err(e) = set_current_dir(
-                                                                        target_path.clone(),
-                                                                    ) {
-                                                                        print_warning(
-                                                                            e,
-                                                                            state.layout.y,
-                                                                        );
-                                                                        break 'command;
-                                                                    }
-                                                                    if let Err(e) = state.chdir(
-                                                                        &target_path,
-                                                                        Move::Jump,
-                                                                    ) {
-                                                                        print_warning(
-                                                                            e,
-                                                                            state.layout.y,
-                                                                        );
-                                                                    }
-                                                                    break 'command;
-                                                                }
-                                                            }
-                                                        }
-                                                    } else {
-                                                        print_warning(
-                                                            "zoxide not installed?",
-                                                            state.layout.y,
-                                                        );
-                                                        break 'command;
-                                                    }
-                                                }
-
-                                                //Execute the command as it is
-                                                execute!(screen, EnterAlternateScreen)?;
-                                                if std::env::set_current_dir(&state.current_dir)
-                                                    .is_err()
-                                                {
-                                                    execute!(screen, EnterAlternateScreen)?;
-                                                    print_warning(
-                                                        "Cannot execute command",
-                                                        state.layout.y,
-                                                    );
-                                                    break 'command;
-                                                }
-                                                if std::process::Command::new(command)
-                                                    .args(&commands[1..])
-                                                    .status()
-                                                    .is_err()
-                                                {
-                                                    execute!(screen, EnterAlternateScreen)?;
-                                                    state.redraw(state.layout.y);
-                                                    print_warning(
-                                                        "Cannot execute command",
-                                                        state.layout.y,
-                                                    );
-                                                    break 'command;
-                                                }
-                                                execute!(screen, EnterAlternateScreen)?;
-                                                hide_cursor();
-                                                info!("SHELL: {:?}", commands);
-                                                state.reload(state.layout.y)?;
-                                                break 'command;
-                                            }
+                                _ => {}
+                            }
 
-                                            _ => continue,
+                            editor.draw();
+                            screen.flush()?;
+                            if let Event::Key(key) = event::read()? {
+                                //Tab switches the mode, and Backspace at the beginning finishes the filter mode.
+                                input = if key.code == KeyCode::Tab {
+                                    mode = mode.next();
+                                    editor = LineEditor::new(
+                                        &format!("filter {}", mode.prompt()),
+                                        editor.text(),
+                                    );
+                                    LineInput::Changed
+                                } else {
+                                    match editor.handle(key) {
+                                        LineInput::Unhandled if key.code == KeyCode::Backspace => {
+                                            LineInput::Enter
                                         }
-                                        screen.flush()?;
+                                        input => input,
                                     }
-                                }
+                                };
                             }
+                        }
+                        hide_cursor();
+                    }
 
-                            //undo
-                            KeyCode::Char('u') => {
-                                let op_len = state.operations.op_list.len();
-                                if op_len <= state.operations.pos {
-                                    print_info("No operations left.", state.layout.y);
+                    //Search forward.
+                    Action::SearchNext => match &state.keyword {
+                        None => {
+                            continue;
+                        }
+                        Some(keyword) => {
+                            let next = state
+                                .list
+                                .iter()
+                                .skip(state.layout.nums.index + 1)
+                                .position(|x| keyword.is_match(&x.file_name));
+                            match next {
+                                None => {
                                     continue;
                                 }
-                                if let Some(op) = state
-                                    .operations
-                                    .op_list
-                                    .get(op_len - state.operations.pos - 1)
-                                {
-                                    let op = op.clone();
-                                    if let Err(e) = state.undo(&op) {
-                                        print_warning(e, state.layout.y);
-                                        continue;
-                                    }
-
-                                    let new_len = state.list.len();
-                                    if new_len == 0 {
-                                        state.layout.nums.reset();
-                                        state.move_cursor(BEGINNING_ROW);
-                                    } else if state.layout.nums.index > new_len - 1 {
-                                        let new_y = state.layout.y
-                                            - (state.layout.nums.index - (new_len - 1)) as u16;
-                                        state.layout.nums.index = new_len - 1;
-                                        state.move_cursor(new_y)
-                                    } else {
-                                        state.move_cursor(state.layout.y);
-                                    }
+                                Some(i) => {
+                                    let i = i + state.layout.nums.index + 1;
+                                    state.layout.nums.skip = i as u16;
+                                    state.layout.nums.index = i;
+                                    state.redraw(BEGINNING_ROW);
                                 }
                             }
+                        }
+                    },
 
-                            //redo
-                            KeyCode::Char('r') if modifiers == KeyModifiers::CONTROL => {
-                                let op_len = state.operations.op_list.len();
-                                if op_len == 0
-                                    || state.operations.pos == 0
-                                    || op_len < state.operations.pos
-                                {
-                                    print_info("No operations left.", state.layout.y);
+                    //Search backward.
+                    Action::SearchPrevious => match &state.keyword {
+                        None => {
+                            continue;
+                        }
+                        Some(keyword) => {
+                            let previous = state
+                                .list
+                                .iter()
+                                .take(state.layout.nums.index)
+                                .rposition(|x| keyword.is_match(&x.file_name));
+                            match previous {
+                                None => {
                                     continue;
                                 }
-                                if let Some(op) =
-                                    state.operations.op_list.get(op_len - state.operations.pos)
-                                {
-                                    let op = op.clone();
-                                    if let Err(e) = state.redo(&op) {
-                                        print_warning(e, state.layout.y);
-                                        continue;
-                                    }
+                                Some(i) => {
+                                    state.layout.nums.skip = i as u16;
+                                    state.layout.nums.index = i;
+                                    state.redraw(BEGINNING_ROW);
+                                }
+                            }
+                        }
+                    },
 
-                                    let new_len = state.list.len();
-                                    if new_len == 0 {
-                                        state.layout.nums.reset();
-                                        state.move_cursor(BEGINNING_ROW);
-                                    } else if state.layout.nums.index > new_len - 1 {
-                                        let new_y = state.layout.y
-                                            - (state.layout.nums.index - (new_len - 1)) as u16;
-                                        state.layout.nums.index = new_len - 1;
-                                        state.move_cursor(new_y)
-                                    } else {
-                                        state.move_cursor(state.layout.y);
-                                    }
+                    //fuzzy finder
+                    Action::Find => {
+                        match finder::find(&state.current_dir, state.layout.show_hidden, &screen)? {
+                            Some(p) => {
+                                if let Err(e) = state.jump_to(&p) {
+                                    state.redraw(state.layout.y);
+                                    print_warning(e, state.layout.y);
                                 }
                             }
+                            None => state.redraw(state.layout.y),
+                        }
+                    }
 
-                            //exit by ZZ
-                            KeyCode::Char('Z') => {
-                                delete_cursor();
+                    //shell mode
+                    Action::Shell => {
+                        delete_cursor();
+                        show_cursor();
+                        match read_command(
+                            &mut state.shell_history,
+                            &state.current_dir,
+                            &state.custom_commands,
+                        )? {
+                            Some(line) => {
+                                if run_command(&mut state, &screen, &line)? {
+                                    break 'main;
+                                }
+                            }
+                            None => {
                                 go_to_and_rest_info();
-                                print!("Z");
-                                show_cursor();
-                                screen.flush()?;
+                                hide_cursor();
+                                state.move_cursor(state.layout.y);
+                            }
+                        }
+                    }
 
-                                if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                                    match code {
-                                        KeyCode::Char('Z') => {
-                                            break 'main;
-                                        }
+                    //undo
+                    Action::Undo => {
+                        let op_len = state.operations.op_list.len();
+                        if op_len <= state.operations.pos {
+                            print_info("No operations left.", state.layout.y);
+                            continue;
+                        }
+                        if let Some(op) = state
+                            .operations
+                            .op_list
+                            .get(op_len - state.operations.pos - 1)
+                        {
+                            let op = op.clone();
+                            if let Err(e) = state.undo(&op) {
+                                print_warning(e, state.layout.y);
+                                continue;
+                            }
 
-                                        _ => {
-                                            go_to_and_rest_info();
-                                            hide_cursor();
-                                            state.move_cursor(state.layout.y);
-                                        }
-                                    }
-                                }
+                            let new_len = state.list.len();
+                            if new_len == 0 {
+                                state.layout.nums.reset();
+                                state.move_cursor(BEGINNING_ROW);
+                            } else if state.layout.nums.index > new_len - 1 {
+                                let new_y = state.layout.y
+                                    - (state.layout.nums.index - (new_len - 1)) as u16;
+                                state.layout.nums.index = new_len - 1;
+                                state.move_cursor(new_y)
+                            } else {
+                                state.move_cursor(state.layout.y);
                             }
+                        }
+                    }
 
-                            //If input does not match any of the defined keys, ignore it.
-                            _ => {
+                    //redo
+                    Action::Redo => {
+                        let op_len = state.operations.op_list.len();
+                        if op_len == 0 || state.operations.pos == 0 || op_len < state.operations.pos
+                        {
+                            print_info("No operations left.", state.layout.y);
+                            continue;
+                        }
+                        if let Some(op) =
+                            state.operations.op_list.get(op_len - state.operations.pos)
+                        {
+                            let op = op.clone();
+                            if let Err(e) = state.redo(&op) {
+                                print_warning(e, state.layout.y);
                                 continue;
                             }
+
+                            let new_len = state.list.len();
+                            if new_len == 0 {
+                                state.layout.nums.reset();
+                                state.move_cursor(BEGINNING_ROW);
+                            } else if state.layout.nums.index > new_len - 1 {
+                                let new_y = state.layout.y
+                                    - (state.layout.nums.index - (new_len - 1)) as u16;
+                                state.layout.nums.index = new_len - 1;
+                                state.move_cursor(new_y)
+                            } else {
+                                state.move_cursor(state.layout.y);
+                            }
+                        }
+                    }
+
+                    //Open a new tab in the current directory.
+                    Action::NewTab => {
+                        if let Err(e) = state.new_tab() {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
+
+                    //Close the current tab.
+                    Action::CloseTab => {
+                        if state.tabs.len() == 1 {
+                            print_info("Cannot close the last tab.", state.layout.y);
+                            continue;
+                        }
+                        if let Err(e) = state.close_tab() {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
+
+                    //Switch to the next/previous tab.
+                    Action::NextTab => {
+                        if state.tabs.len() == 1 {
+                            continue;
+                        }
+                        if let Err(e) = state.next_tab() {
+                            print_warning(e, state.layout.y);
                         }
                     }
-                    //Other modifiers disable commands when pressed.
-                    _ => {
+
+                    Action::PreviousTab => {
+                        if state.tabs.len() == 1 {
+                            continue;
+                        }
+                        if let Err(e) = state.previous_tab() {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
+
+                    //Toggle the dual-pane mode.
+                    Action::ToggleDualPane => {
+                        if let Err(e) = state.toggle_dual_pane() {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
+
+                    //Move the focus to the other pane.
+                    Action::SwitchPane => {
+                        if let Err(e) = state.switch_pane() {
+                            print_warning(e, state.layout.y);
+                        }
+                    }
+
+                    //Show the jobs view.
+                    Action::Jobs => {
+                        state.show_jobs(&screen)?;
+                        state.reload_and_fit_cursor()?;
+                    }
+
+                    //run the line bound by `:<line>` in the keymap
+                    Action::Command(i) => {
+                        let line = state.keymap.line(i).to_owned();
+                        if run_command(&mut state, &screen, &line)? {
+                            break 'main;
+                        }
+                    }
+
+                    //exit by ZZ
+                    Action::Quit => {
+                        if state.jobs.is_running() {
+                            print_warning(JOBS_RUNNING_WARNING, state.layout.y);
+                            continue;
+                        }
+                        break 'main;
+                    }
+
+                    //Scroll down/up the preview text.
+                    Action::ScrollPreviewDown => {
+                        if state.layout.preview {
+                            state.scroll_down_preview(state.layout.y);
+                        }
+                    }
+
+                    Action::ScrollPreviewUp => {
+                        if state.layout.preview {
+                            state.scroll_up_preview(state.layout.y);
+                        }
+                    }
+
+                    Action::Nop => {
                         continue;
                     }
                 }
@@ -1642,6 +1475,18 @@ fn _run(mut state: State, session_path: PathBuf) -> Result<(), FxError> {
                     };
 
                     state.refresh(new_column, new_row, cursor_pos)?;
+                } else if state.pane.is_some() {
+                    let cursor_pos = if state.layout.y < row {
+                        state.layout.y
+                    } else {
+                        let diff = state.layout.y + 1 - row;
+                        state.layout.nums.index -= diff as usize;
+                        row - 1
+                    };
+                    if state.layout.x_offset != 0 {
+                        state.layout.set_x_offset(column / 2);
+                    }
+                    state.refresh(column / 2, row, cursor_pos)?;
                 } else {
                     let cursor_pos = if state.layout.y < row {
                         state.layout.y
@@ -1658,7 +1503,10 @@ fn _run(mut state: State, session_path: PathBuf) -> Result<(), FxError> {
         }
     }
 
-    //Save session, restore screen state and cursor
+    //Save session and operations, restore screen state and cursor
+    state
+        .operations
+        .write(&session_path.with_file_name(OPERATIONS_FILE))?;
     state.write_session(session_path)?;
     execute!(screen, LeaveAlternateScreen)?;
     write!(screen, "{}", RestorePosition)?;
@@ -1668,6 +1516,341 @@ fn _run(mut state: State, session_path: PathBuf) -> Result<(), FxError> {
     Ok(())
 }
 
+/// Show the permissions editor of the items, and apply the change.
+fn edit_permissions(state: &mut State, screen: &Stdout, items: &[ItemInfo]) -> Result<(), FxError> {
+    let editor = permissions::edit_permissions(items, screen)?;
+    let result = editor.map(|editor| state.change_permissions(items, &editor));
+    state.reload(state.layout.y)?;
+    match result {
+        Some(Ok(n)) => print_info(format!("{} item(s) changed", n), state.layout.y),
+        Some(Err(e)) => print_warning(e, state.layout.y),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Message of the current sort order, such as `Sort by size (reverse, mixed)`.
+fn sort_message(layout: &Layout) -> String {
+    let mut options = Vec::new();
+    if layout.sort_reverse {
+        options.push("reverse");
+    }
+    if !layout.dirs_first {
+        options.push("mixed");
+    }
+    if options.is_empty() {
+        format!("Sort by {}", layout.sort_by.name())
+    } else {
+        format!("Sort by {} ({})", layout.sort_by.name(), options.join(", "))
+    }
+}
+
+/// Run the command line of the shell mode. Returns `true` if the app should quit.
+fn run_command(state: &mut State, mut screen: &Stdout, line: &str) -> Result<bool, FxError> {
+    //Set the command and argument(s).
+    let commands: Vec<&str> = line.split_whitespace().collect();
+    if commands.is_empty() {
+        go_to_and_rest_info();
+        state.move_cursor(state.layout.y);
+        return Ok(false);
+    }
+    let command = commands[0];
+
+    if commands.len() == 1 {
+        if command == "q" {
+            //quit
+            if state.jobs.is_running() {
+                print_warning(JOBS_RUNNING_WARNING, state.layout.y);
+                return Ok(false);
+            }
+            return Ok(true);
+        } else if command == "cd" || command == "z" {
+            //go to the home directory
+            let home_dir = dirs::home_dir()
+                .ok_or_else(|| FxError::Dirs("Cannot read home dir.".to_string()))?;
+            if let Err(e) = state.chdir(&home_dir, Move::Jump) {
+                print_warning(e, state.layout.y);
+            }
+            return Ok(false);
+        } else if command == "e" {
+            //reload current dir
+            state.keyword = None;
+            state.layout.nums.reset();
+            state.reload(BEGINNING_ROW)?;
+            return Ok(false);
+        } else if command == "h" {
+            //show help
+            state.show_help(screen)?;
+            state.redraw(state.layout.y);
+            return Ok(false);
+        } else if command == "trash" {
+            //show the trash view
+            state.show_trash(screen)?;
+            state.reload_and_fit_cursor()?;
+            return Ok(false);
+        } else if command == "empty" {
+            //empty the trash dir
+            state.empty_trash(screen)?;
+            return Ok(false);
+        }
+    }
+
+    //rename the selected items (or the current item) by the substitution or template
+    if command == "rename" && commands.len() >= 2 {
+        let pattern = line
+            .trim_start()
+            .strip_prefix("rename")
+            .unwrap_or_default()
+            .trim();
+        let targets: Vec<ItemInfo> = if state.list.iter().any(|item| item.selected) {
+            state
+                .list
+                .iter()
+                .filter(|item| item.selected)
+                .cloned()
+                .collect()
+        } else {
+            match state.get_item() {
+                Ok(item) => vec![item.clone()],
+                Err(_) => return Ok(false),
+            }
+        };
+        match state.rename_by_pattern(&targets, pattern, screen) {
+            Ok(n) => {
+                state.reload(state.layout.y)?;
+                print_info(format!("{} item(s) renamed", n), state.layout.y);
+            }
+            Err(e) => print_warning(e, state.layout.y),
+        }
+        return Ok(false);
+    }
+
+    //change the sort key
+    if command == "sort" && commands.len() == 2 {
+        match SortKey::from_name(commands[1]) {
+            Some(key) => {
+                state.layout.sort_by = key;
+                state.layout.nums.reset();
+                state.reorder(BEGINNING_ROW);
+                print_info(sort_message(&state.layout), state.layout.y);
+            }
+            None => print_warning(
+                "Sort key must be name, time, size, extension, created, accessed or type.",
+                state.layout.y,
+            ),
+        }
+        return Ok(false);
+    }
+
+    //save or remove the view settings of the current directory
+    if command == "view" && commands.len() == 2 {
+        match commands[1] {
+            "save" => {
+                state.save_dir_view();
+                print_info("View saved for this directory.", state.layout.y);
+            }
+            "reset" => {
+                if state.remove_dir_view()? {
+                    print_info("View of this directory removed.", state.layout.y);
+                } else {
+                    print_info("No view saved for this directory.", state.layout.y);
+                }
+            }
+            _ => print_warning("Use :view save or :view reset.", state.layout.y),
+        }
+        return Ok(false);
+    }
+
+    //search the contents of the files under the current directory
+    if command == "grep" && commands.len() >= 2 {
+        let pattern = line.trim_start()["grep".len()..].trim();
+        let mut view = match GrepView::new(&state.current_dir, pattern, state.layout.show_hidden) {
+            Ok(view) => view,
+            Err(e) => {
+                print_warning(e, state.layout.y);
+                return Ok(false);
+            }
+        };
+        //Return to the results after editing the file.
+        while let Some(hit) = view.show(&state.layout, screen)? {
+            execute!(screen, EnterAlternateScreen)?;
+            if let Err(e) = state.open_file_at(&hit.path, hit.line) {
+                execute!(screen, EnterAlternateScreen)?;
+                state.reload(state.layout.y)?;
+                print_warning(e, state.layout.y);
+                return Ok(false);
+            }
+            execute!(screen, EnterAlternateScreen)?;
+            hide_cursor();
+        }
+        state.reload(state.layout.y)?;
+        return Ok(false);
+    }
+
+    //zoxide jump
+    if command == "z" && commands.len() == 2 {
+        //Change directory using zoxide
+        if let Ok(output) = std::process::Command::new("zoxide")
+            .args(["query", commands[1].trim()])
+            .output()
+        {
+            let output = output.stdout;
+            if output.is_empty() {
+                print_warning("Keyword does not match the database.", state.layout.y);
+                return Ok(false);
+            } else {
+                let target_dir = std::str::from_utf8(&output);
+                match target_dir {
+                    Err(e) => {
+                        print_warning(e, state.layout.y);
+                        return Ok(false);
+                    }
+                    Ok(target_dir) => {
+                        let target_path = PathBuf::from(target_dir.trim());
+                        if let Err(e) = state.chdir(&target_path, Move::Jump) {
+                            print_warning(e, state.layout.y);
+                        }
+                        return Ok(false);
+                    }
+                }
+            }
+        } else {
+            print_warning("zoxide not installed?", state.layout.y);
+            return Ok(false);
+        }
+    }
+
+    //Custom command in the config, with the rest of the line appended.
+    if let Some(custom) = state.custom_commands.get(command).cloned() {
+        let rest = line.trim_start()[command.len()..].trim();
+        let line = format!("{} {}", custom.cmd(), rest);
+        if custom.background() {
+            let targets = state.shell_targets();
+            if let Some((program, args)) = targets.to_command(&line, state.use_shell) {
+                info!("CUSTOM COMMAND: {} {:?}", program, args);
+                state.jobs.spawn(
+                    JobKind::Command {
+                        name: command.to_owned(),
+                        program,
+                        args,
+                        envs: targets.envs(),
+                        dir: state.current_dir.clone(),
+                    },
+                    Vec::new(),
+                );
+                print_info(format!("{}: started", command), state.layout.y);
+            }
+            return Ok(false);
+        }
+        execute_line(state, screen, &line, custom.wait(), custom.reload())?;
+        return Ok(false);
+    }
+
+    //With `!` at the beginning, the output is captured and shown in the pager.
+    match line.trim_start().strip_prefix('!') {
+        Some(line) => capture_output(state, screen, line.trim_start())?,
+        None => execute_line(state, screen, line, false, true)?,
+    }
+    Ok(false)
+}
+
+/// Execute the line in the terminal, expanding the placeholders.
+/// If `wait` is true, wait for Enter after the command exits.
+fn execute_line(
+    state: &mut State,
+    mut screen: &Stdout,
+    line: &str,
+    wait: bool,
+    reload: bool,
+) -> Result<(), FxError> {
+    let targets = state.shell_targets();
+    let (program, args) = match targets.to_command(line, state.use_shell) {
+        Some(command) => command,
+        None => {
+            go_to_and_rest_info();
+            state.move_cursor(state.layout.y);
+            return Ok(());
+        }
+    };
+    if std::env::set_current_dir(&state.current_dir).is_err() {
+        print_warning("Cannot execute command", state.layout.y);
+        return Ok(());
+    }
+    info!("SHELL: {} {:?}", program, args);
+
+    execute!(screen, EnterAlternateScreen)?;
+    if std::process::Command::new(&program)
+        .args(&args)
+        .envs(targets.envs())
+        .status()
+        .is_err()
+    {
+        execute!(screen, EnterAlternateScreen)?;
+        state.redraw(state.layout.y);
+        print_warning("Cannot execute command", state.layout.y);
+        return Ok(());
+    }
+    if wait {
+        print!("\r\nPress Enter to return to felix.");
+        screen.flush()?;
+        loop {
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }) = event::read()?
+            {
+                break;
+            }
+        }
+    }
+    execute!(screen, EnterAlternateScreen)?;
+    hide_cursor();
+    if reload {
+        state.reload(state.layout.y)?;
+    } else {
+        state.redraw(state.layout.y);
+    }
+    Ok(())
+}
+
+/// Execute the line, and show the output (stdout, then stderr) in the pager.
+fn capture_output(state: &mut State, mut screen: &Stdout, line: &str) -> Result<(), FxError> {
+    let targets = state.shell_targets();
+    let (program, args) = match targets.to_command(line, state.use_shell) {
+        Some(command) => command,
+        None => {
+            go_to_and_rest_info();
+            state.move_cursor(state.layout.y);
+            return Ok(());
+        }
+    };
+    info!("SHELL: {} {:?}", program, args);
+
+    print_info("Running...", state.layout.y);
+    screen.flush()?;
+    let output = std::process::Command::new(&program)
+        .args(&args)
+        .envs(targets.envs())
+        .current_dir(&state.current_dir)
+        .stdin(std::process::Stdio::null())
+        .output();
+    match output {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            let title = match output.status.code() {
+                Some(0) => line.to_owned(),
+                Some(code) => format!("{} [exit status: {}]", line, code),
+                None => format!("{} [terminated]", line),
+            };
+            state.show_output(screen, &title, &text)?;
+            state.reload(state.layout.y)?;
+        }
+        Err(e) => print_warning(e, state.layout.y),
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,14 +1,17 @@
 use super::errors::FxError;
 use super::layout::Split;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub const SESSION_FILE: &str = ".session";
 pub const SESSION_EXAMPLE: &str = "sort_by = \"Name\"
 show_hidden = false
 preview = false
 split = Vertical
+sort_reverse = false
+dirs_first = true
 ";
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -17,12 +20,71 @@ pub struct Session {
     pub show_hidden: bool,
     pub preview: Option<bool>,
     pub split: Option<Split>,
+    pub sort_reverse: Option<bool>,
+    pub dirs_first: Option<bool>,
+    /// View settings saved by `:view save`, keyed by the canonical path of the directory.
+    pub dirs: Option<BTreeMap<PathBuf, DirView>>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+/// View settings of a directory, which override the global ones while in it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirView {
+    pub sort_by: SortKey,
+    pub sort_reverse: bool,
+    pub show_hidden: bool,
+    pub preview: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortKey {
+    /// Natural order, such as `v2` before `v10`.
     Name,
+    /// Modified time, newer first.
     Time,
+    /// Larger first.
+    Size,
+    Extension,
+    /// Created time, newer first.
+    Created,
+    /// Accessed time, newer first.
+    Accessed,
+    /// Directory, file and symlink.
+    Type,
+}
+
+/// Sort keys in the order switched by `t`, and the names used in `:sort`.
+const SORT_KEYS: &[(&str, SortKey)] = &[
+    ("name", SortKey::Name),
+    ("time", SortKey::Time),
+    ("size", SortKey::Size),
+    ("extension", SortKey::Extension),
+    ("created", SortKey::Created),
+    ("accessed", SortKey::Accessed),
+    ("type", SortKey::Type),
+];
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        let i = SORT_KEYS
+            .iter()
+            .position(|(_, key)| *key == self)
+            .unwrap_or(0);
+        SORT_KEYS[(i + 1) % SORT_KEYS.len()].1
+    }
+
+    pub fn name(self) -> &'static str {
+        SORT_KEYS
+            .iter()
+            .find(|(_, key)| *key == self)
+            .map_or("name", |(name, _)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SORT_KEYS
+            .iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|(_, key)| *key)
+    }
 }
 
 pub fn read_session(session_path: &Path) -> Result<Session, FxError> {
@@ -34,6 +96,9 @@ pub fn read_session(session_path: &Path) -> Result<Session, FxError> {
                 show_hidden: true,
                 preview: Some(false),
                 split: Some(Split::Vertical),
+                sort_reverse: Some(false),
+                dirs_first: Some(true),
+                dirs: None,
             }),
         },
         Err(_) => Ok(Session {
@@ -41,6 +106,9 @@ pub fn read_session(session_path: &Path) -> Result<Session, FxError> {
             show_hidden: true,
             preview: Some(false),
             split: Some(Split::Vertical),
+            sort_reverse: Some(false),
+            dirs_first: Some(true),
+            dirs: None,
         }),
     }
 }
@@ -49,3 +117,38 @@ pub fn make_session(session_file: &Path) -> Result<(), FxError> {
     std::fs::write(session_file, SESSION_EXAMPLE)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_views() {
+        let mut dirs = BTreeMap::new();
+        dirs.insert(
+            PathBuf::from("/home/user/Downloads"),
+            DirView {
+                sort_by: SortKey::Time,
+                sort_reverse: false,
+                show_hidden: false,
+                preview: true,
+            },
+        );
+        let session = Session {
+            sort_by: SortKey::Name,
+            show_hidden: false,
+            preview: Some(false),
+            split: Some(Split::Vertical),
+            sort_reverse: Some(false),
+            dirs_first: Some(true),
+            dirs: Some(dirs.clone()),
+        };
+        let serialized = serde_yaml::to_string(&session).unwrap();
+        let deserialized: Session = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.dirs, Some(dirs));
+
+        //Sessions written before the per-directory views are still read.
+        let old: Session = serde_yaml::from_str("sort_by: Time\nshow_hidden: true\n").unwrap();
+        assert_eq!(old.dirs, None);
+    }
+}
@@ -0,0 +1,556 @@
+use super::config::CustomCommand;
+use super::errors::FxError;
+use super::line_editor::{LineEditor, LineInput};
+
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File to save the history of the shell mode, next to the session file.
+pub const HISTORY_FILE: &str = ".shell_history";
+const HISTORY_LIMIT: usize = 1000;
+
+/// Internal commands of the shell mode, for the completion.
+const INTERNAL_COMMANDS: &[&str] = &[
+    "cd", "e", "empty", "grep", "h", "q", "rename", "sort", "trash", "view", "z",
+];
+
+/// History of the shell mode, one command per line in the file.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+    /// Position while going through the history by Up/Down.
+    index: usize,
+    /// The line being typed before going through the history.
+    draft: String,
+}
+
+/// Items referred by the placeholders and environment variables in the shell mode.
+#[derive(Debug, Default)]
+pub struct Targets {
+    /// Selected items, or the current item if nothing is selected.
+    pub selected: Vec<PathBuf>,
+    pub current: Option<PathBuf>,
+    pub dir: PathBuf,
+}
+
+/// Candidates of Tab completion, cycled by pressing Tab again.
+#[derive(Debug)]
+pub struct Completion {
+    start: usize,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+impl History {
+    pub fn read(p: &Path) -> Self {
+        let entries: Vec<String> = std::fs::read_to_string(p)
+            .map(|s| s.lines().map(|line| line.to_owned()).collect())
+            .unwrap_or_default();
+        History {
+            index: entries.len(),
+            entries,
+            path: Some(p.to_path_buf()),
+            draft: String::new(),
+        }
+    }
+
+    /// Add the command and save the history, skipping the same one as the last.
+    pub fn push(&mut self, line: &str) -> Result<(), FxError> {
+        let line = line.trim();
+        if !line.is_empty() && self.entries.last().map(|s| s.as_str()) != Some(line) {
+            self.entries.push(line.to_owned());
+            if self.entries.len() > HISTORY_LIMIT {
+                self.entries.drain(..self.entries.len() - HISTORY_LIMIT);
+            }
+        }
+        self.reset();
+        if let Some(path) = &self.path {
+            let mut content = self.entries.join("\n");
+            content.push('\n');
+            std::fs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Start going through the history from the latest.
+    pub fn reset(&mut self) {
+        self.index = self.entries.len();
+        self.draft.clear();
+    }
+
+    /// Go back to the older command, keeping the current line as the draft.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        if self.index == 0 {
+            return None;
+        }
+        if self.index == self.entries.len() {
+            self.draft = current.to_owned();
+        }
+        self.index -= 1;
+        self.entries.get(self.index).map(|s| s.as_str())
+    }
+
+    /// Go forward to the newer command, or the draft after the latest one.
+    pub fn next(&mut self) -> Option<&str> {
+        if self.index >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        match self.entries.get(self.index) {
+            Some(entry) => Some(entry.as_str()),
+            None => Some(&self.draft),
+        }
+    }
+
+    /// Index of the latest command containing the query, before `before`.
+    fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    /// Incremental search of the history by Ctrl-R.
+    /// Ctrl-R again goes to the older match, and Enter returns the match.
+    pub fn reverse_search(&self) -> Result<Option<String>, FxError> {
+        let mut editor = LineEditor::new("(reverse-i-search)`", "");
+        let mut found = self.search("", self.entries.len());
+        loop {
+            let matched = found.map_or("", |i| self.entries[i].as_str());
+            editor.set_suffix(&format!("': {}", matched));
+            editor.draw();
+            std::io::stdout().flush()?;
+
+            if let Event::Key(key) = crossterm::event::read()? {
+                if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    if let Some(i) = found {
+                        found = self.search(editor.text(), i).or(found);
+                    }
+                    continue;
+                }
+                match editor.handle(key) {
+                    LineInput::Enter => return Ok(Some(matched.to_owned())),
+                    LineInput::Cancel => return Ok(None),
+                    LineInput::Changed => {
+                        found = self.search(editor.text(), self.entries.len());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Targets {
+    /// Expand `%s` (selected items), `%f` (current item), `%d` (current directory) and `%%` in the arguments.
+    /// `%s` as a whole argument becomes one argument per item, so that paths with spaces are kept as they are.
+    pub fn expand_args(&self, args: &[&str]) -> Vec<String> {
+        let mut result = Vec::new();
+        for arg in args {
+            if *arg == "%s" {
+                result.extend(
+                    self.selected
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string()),
+                );
+            } else {
+                result.push(self.expand(arg, |p| p.to_string_lossy().to_string()));
+            }
+        }
+        result
+    }
+
+    /// Program and arguments to execute the line, with the placeholders expanded.
+    /// With `use_shell`, the line is passed to `$SHELL -c`.
+    pub fn to_command(&self, line: &str, use_shell: bool) -> Option<(String, Vec<String>)> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        if use_shell {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_owned());
+            Some((shell, vec!["-c".to_owned(), self.expand_for_shell(line)]))
+        } else {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            Some((words[0].to_owned(), self.expand_args(&words[1..])))
+        }
+    }
+
+    /// Expand the placeholders in the whole line for `$SHELL -c`, quoting each path.
+    pub fn expand_for_shell(&self, line: &str) -> String {
+        self.expand(line, |p| quote(&p.to_string_lossy()))
+    }
+
+    fn expand(&self, s: &str, to_str: impl Fn(&Path) -> String) -> String {
+        let mut result = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('s') => {
+                    let selected: Vec<String> = self.selected.iter().map(|p| to_str(p)).collect();
+                    result.push_str(&selected.join(" "));
+                }
+                Some('f') => {
+                    if let Some(current) = &self.current {
+                        result.push_str(&to_str(current));
+                    }
+                }
+                Some('d') => result.push_str(&to_str(&self.dir)),
+                Some('%') => result.push('%'),
+                Some(c) => {
+                    result.push('%');
+                    result.push(c);
+                }
+                None => result.push('%'),
+            }
+        }
+        result
+    }
+
+    /// `$fx_selected` (newline-separated paths) and `$fx_current` for the command.
+    pub fn envs(&self) -> Vec<(&'static str, String)> {
+        let selected: Vec<String> = self
+            .selected
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        vec![
+            ("fx_selected", selected.join("\n")),
+            (
+                "fx_current",
+                self.current
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+/// Quote the string with single quotes for the POSIX shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Read the command line of the shell mode, with the history and completion.
+/// Returns `None` if cancelled by Esc, or Backspace at the beginning.
+pub fn read_command(
+    history: &mut History,
+    current_dir: &Path,
+    custom_commands: &BTreeMap<String, CustomCommand>,
+) -> Result<Option<String>, FxError> {
+    let mut editor = LineEditor::new(":", "");
+    let mut completion = None;
+    history.reset();
+    loop {
+        editor.draw();
+        std::io::stdout().flush()?;
+        if let Event::Key(key) = crossterm::event::read()? {
+            //History and completion are handled before the line editor.
+            if key.code != KeyCode::Tab {
+                completion = None;
+            }
+            match key.code {
+                KeyCode::Up => {
+                    if let Some(line) = history.previous(editor.text()) {
+                        editor.set_text(line);
+                    }
+                    continue;
+                }
+                KeyCode::Down => {
+                    if let Some(line) = history.next() {
+                        editor.set_text(line);
+                    }
+                    continue;
+                }
+                KeyCode::Tab => {
+                    complete(&mut editor, &mut completion, current_dir, custom_commands);
+                    continue;
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if let Some(line) = history.reverse_search()? {
+                        editor.set_text(&line);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            match editor.handle(key) {
+                LineInput::Enter => {
+                    let line = editor.text().to_owned();
+                    history.push(&line)?;
+                    return Ok(Some(line));
+                }
+                LineInput::Cancel => return Ok(None),
+                LineInput::Unhandled if key.code == KeyCode::Backspace => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Complete the word before the cursor by Tab.
+/// The first word is completed with the internal commands, custom commands and executables in `$PATH`,
+/// and others with the paths relative to the current directory.
+fn complete(
+    editor: &mut LineEditor,
+    cycle: &mut Option<Completion>,
+    current_dir: &Path,
+    custom_commands: &BTreeMap<String, CustomCommand>,
+) {
+    if let Some(completion) = cycle {
+        completion.index = (completion.index + 1) % completion.candidates.len();
+        editor.replace_before_cursor(completion.start, &completion.candidates[completion.index]);
+        return;
+    }
+
+    let before = editor.before_cursor();
+    let start = before.rfind(char::is_whitespace).map_or(0, |i| {
+        i + before[i..].chars().next().map_or(1, |c| c.len_utf8())
+    });
+    let word = &before[start..];
+    let candidates = if before[..start].trim().is_empty() && !word.contains('/') {
+        command_candidates(word, custom_commands)
+    } else {
+        path_candidates(word, current_dir)
+    };
+
+    match candidates.len() {
+        0 => {}
+        1 => {
+            let mut completed = candidates[0].clone();
+            if !completed.ends_with('/') {
+                completed.push(' ');
+            }
+            editor.replace_before_cursor(start, &completed);
+        }
+        _ => {
+            let prefix = common_prefix(&candidates);
+            if prefix.len() > word.len() {
+                editor.replace_before_cursor(start, &prefix);
+            } else {
+                editor.replace_before_cursor(start, &candidates[0]);
+                *cycle = Some(Completion {
+                    start,
+                    candidates,
+                    index: 0,
+                });
+            }
+        }
+    }
+}
+
+fn command_candidates(
+    word: &str,
+    custom_commands: &BTreeMap<String, CustomCommand>,
+) -> Vec<String> {
+    let mut candidates: BTreeSet<String> = INTERNAL_COMMANDS
+        .iter()
+        .copied()
+        .chain(custom_commands.keys().map(|name| name.as_str()))
+        .filter(|command| command.starts_with(word))
+        .map(|command| command.to_string())
+        .collect();
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            let entries = match std::fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(word) && is_executable(&entry.path()) {
+                    candidates.insert(name);
+                }
+            }
+        }
+    }
+    candidates.into_iter().collect()
+}
+
+fn path_candidates(word: &str, current_dir: &Path) -> Vec<String> {
+    let (dir_part, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let dir = if let Some(rest) = dir_part.strip_prefix("~/") {
+        match dirs::home_dir() {
+            Some(home) => home.join(rest),
+            None => return vec![],
+        }
+    } else {
+        current_dir.join(dir_part)
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut candidates: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            //Hidden items are completed only when the prefix starts with a dot.
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let mut candidate = format!("{}{}", dir_part, name);
+            if entry.path().is_dir() {
+                candidate.push('/');
+            }
+            Some(candidate)
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = candidates[0].clone();
+    for candidate in candidates.iter().skip(1) {
+        let len = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(candidate.len()), |((i, _), _)| i);
+        prefix.truncate(len);
+    }
+    prefix
+}
+
+#[cfg(target_family = "unix")]
+fn is_executable(p: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(p)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_family = "unix"))]
+fn is_executable(p: &Path) -> bool {
+    p.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let p = std::env::temp_dir().join("felix_test_history");
+        let _ = std::fs::remove_file(&p);
+        let mut history = History::read(&p);
+        history.push("ls").unwrap();
+        history.push("echo a").unwrap();
+        history.push("echo a").unwrap();
+
+        let mut history = History::read(&p);
+        assert_eq!(history.previous("ec"), Some("echo a"));
+        assert_eq!(history.previous("echo a"), Some("ls"));
+        assert_eq!(history.previous("ls"), None);
+        assert_eq!(history.next(), Some("echo a"));
+        assert_eq!(history.next(), Some("ec"));
+        assert_eq!(history.next(), None);
+        assert_eq!(history.search("s", 2), Some(0));
+        assert_eq!(history.search("a", 1), None);
+
+        std::fs::remove_file(&p).unwrap();
+    }
+
+    #[test]
+    fn test_expand_args() {
+        let targets = Targets {
+            selected: vec![PathBuf::from("/tmp/a b"), PathBuf::from("/tmp/c")],
+            current: Some(PathBuf::from("/tmp/a b")),
+            dir: PathBuf::from("/tmp"),
+        };
+        assert_eq!(
+            targets.expand_args(&["czf", "%d/out.tgz", "%s"]),
+            vec!["czf", "/tmp/out.tgz", "/tmp/a b", "/tmp/c"]
+        );
+        assert_eq!(
+            targets.expand_args(&["%f", "100%%", "%x"]),
+            vec!["/tmp/a b", "100%", "%x"]
+        );
+        assert_eq!(targets.envs()[0].1, "/tmp/a b\n/tmp/c");
+
+        let targets = Targets {
+            selected: vec![PathBuf::from("/tmp/it's"), PathBuf::from("/tmp/c")],
+            current: Some(PathBuf::from("/tmp/it's")),
+            dir: PathBuf::from("/tmp"),
+        };
+        assert_eq!(
+            targets.expand_for_shell("ls %s | wc -l > %d/count"),
+            r"ls '/tmp/it'\''s' '/tmp/c' | wc -l > '/tmp'/count"
+        );
+        assert_eq!(
+            targets.expand_for_shell("echo '%%' %f"),
+            r"echo '%' '/tmp/it'\''s'"
+        );
+        assert_eq!(
+            targets.to_command("git add %s", false),
+            Some((
+                "git".to_owned(),
+                vec![
+                    "add".to_owned(),
+                    "/tmp/it's".to_owned(),
+                    "/tmp/c".to_owned()
+                ]
+            ))
+        );
+        assert_eq!(targets.to_command("  ", true), None);
+    }
+
+    #[test]
+    fn test_complete() {
+        let base = std::env::temp_dir().join("felix_test_complete");
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("dir_a")).unwrap();
+        std::fs::write(base.join("file_1"), "").unwrap();
+        std::fs::write(base.join("file_2"), "").unwrap();
+        std::fs::write(base.join("dir_a/inner"), "").unwrap();
+
+        let custom_commands = BTreeMap::new();
+        let mut cycle = None;
+        let mut editor = LineEditor::new(":", "cat d");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "cat dir_a/");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "cat dir_a/inner ");
+
+        //Common prefix first, then cycle through the candidates.
+        let mut editor = LineEditor::new(":", "cat f");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "cat file_");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "cat file_1");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "cat file_2");
+
+        let mut cycle = None;
+        let mut editor = LineEditor::new(":", "tra");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert!(editor.text().starts_with("tra"));
+
+        //Custom commands are completed as well as the internal ones.
+        let mut custom_commands = BTreeMap::new();
+        custom_commands.insert(
+            "felix-test-command".to_owned(),
+            CustomCommand::Line("echo %s".to_owned()),
+        );
+        let mut cycle = None;
+        let mut editor = LineEditor::new(":", "felix-test-c");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert_eq!(editor.text(), "felix-test-command ");
+        let mut editor = LineEditor::new(":", "vie");
+        complete(&mut editor, &mut cycle, &base, &custom_commands);
+        assert!(editor.text().starts_with("view"));
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
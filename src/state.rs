@@ -1,50 +1,78 @@
 use super::config::*;
 use super::errors::FxError;
 use super::functions::*;
-use super::help::HELP;
+use super::help::help_text;
+use super::job::*;
+use super::keymap::Keymap;
 use super::layout::*;
+use super::line_editor::truncate_to_width;
 use super::magic_image::is_supported_image_type;
-use super::magic_packed;
+use super::matcher::{Matcher, SearchMode};
 use super::nums::*;
 use super::op::*;
+use super::permissions::{chmod, chown, PermissionsEditor};
+use super::rename::Renamer;
 use super::session::*;
+use super::shell::{History, Targets};
+use super::tab::*;
 use super::term::*;
+use super::trash;
 
 use chrono::prelude::*;
 use crossterm::event::{Event, KeyCode, KeyEvent};
 use crossterm::style::Stylize;
 use log::{error, info};
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::ffi::OsStr;
 use std::fs;
 use std::io::Stdout;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
-use std::time::UNIX_EPOCH;
+use std::time::{SystemTime, UNIX_EPOCH};
 use syntect::highlighting::{Theme, ThemeSet};
+use unicode_width::UnicodeWidthStr;
 
 #[cfg(target_family = "unix")]
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 
 pub const FELIX: &str = "felix";
 pub const BEGINNING_ROW: u16 = 3;
 pub const EMPTY_WARNING: &str = "Are you sure to empty the trash directory? (if yes: y)";
-const TIME_PREFIX: usize = 11;
 
 #[derive(Debug)]
 pub struct State {
     pub list: Vec<ItemInfo>,
     pub current_dir: PathBuf,
     pub trash_dir: PathBuf,
+    pub xdg_trash: bool,
+    pub use_shell: bool,
+    pub custom_commands: BTreeMap<String, CustomCommand>,
     pub default: String,
     pub commands: Option<BTreeMap<String, String>>,
+    pub keymap: Keymap,
     pub registered: Vec<ItemInfo>,
+    pub cut: bool,
+    pub jobs: Jobs,
     pub operations: Operation,
+    pub shell_history: History,
     pub c_memo: Vec<StateMemo>,
     pub p_memo: Vec<StateMemo>,
-    pub keyword: Option<String>,
+    pub keyword: Option<Matcher>,
+    /// Items not matching the filter are hidden from the list, until the directory changes.
+    pub filter: Option<Matcher>,
+    pub search_mode: SearchMode,
+    pub tabs: Vec<Tab>,
+    pub tab_index: usize,
+    pub pane: Option<Pane>,
+    /// View settings saved for directories, keyed by the canonical path.
+    pub dir_views: BTreeMap<PathBuf, DirView>,
+    /// Global view settings while in a directory with the saved view, restored when leaving it.
+    pub global_view: Option<DirView>,
+    /// User and group names shown in the footer and the owner column.
+    pub owner_names: OwnerNames,
     pub layout: Layout,
 }
 
@@ -57,6 +85,8 @@ pub struct ItemInfo {
     pub file_size: u64,
     pub file_ext: Option<String>,
     pub modified: Option<String>,
+    pub created: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
     pub is_hidden: bool,
     pub selected: bool,
     pub matches: bool,
@@ -64,6 +94,13 @@ pub struct ItemInfo {
     pub preview_scroll: usize,
     pub content: Option<String>,
     pub permissions: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// `user group`, read only if the column is shown.
+    pub owner: Option<String>,
+    pub link_target: Option<PathBuf>,
+    /// Number of items in the directory, read only if the column is shown.
+    pub dir_count: Option<usize>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -105,6 +142,7 @@ impl State {
                 Config::default()
             }
         };
+        let keymap = Keymap::new(&config.keymap, &config.select_keymap)?;
         let session = read_session(session_path)?;
         let (original_column, original_row) = terminal_size()?;
 
@@ -118,7 +156,11 @@ impl State {
             return Err(FxError::TooSmallWindowSize);
         };
 
-        let (time_start, name_max) = make_layout(original_column);
+        let columns = config
+            .columns
+            .clone()
+            .unwrap_or_else(|| vec![Column::Mtime]);
+        let (column_starts, name_max) = make_layout(original_column, &columns);
 
         let ts = set_theme(&config);
         let split = session.split.unwrap_or(Split::Vertical);
@@ -129,29 +171,37 @@ impl State {
         Ok(State {
             list: Vec::new(),
             registered: Vec::new(),
-            operations: Operation {
-                pos: 0,
-                op_list: Vec::new(),
-            },
+            cut: false,
+            jobs: Jobs::default(),
+            operations: Operation::default(),
+            shell_history: History::default(),
             current_dir: PathBuf::new(),
             trash_dir: PathBuf::new(),
+            xdg_trash: cfg!(target_family = "unix") && config.xdg_trash.unwrap_or(false),
+            use_shell: config.use_shell.unwrap_or(false),
+            custom_commands: config.custom_commands.clone().unwrap_or_default(),
             default: config
                 .default
                 .unwrap_or_else(|| env::var("EDITOR").unwrap_or_default()),
             commands: to_extension_map(&config.exec),
+            keymap,
             layout: Layout {
                 nums: Num::new(),
                 y: BEGINNING_ROW,
                 terminal_row: original_row,
                 terminal_column: original_column,
+                x_offset: 0,
                 name_max_len: name_max,
-                time_start_pos: time_start,
+                columns,
+                column_starts,
                 colors: ConfigColor {
                     dir_fg: config.color.dir_fg,
                     file_fg: config.color.file_fg,
                     symlink_fg: config.color.symlink_fg,
                 },
                 sort_by: session.sort_by,
+                sort_reverse: session.sort_reverse.unwrap_or(false),
+                dirs_first: session.dirs_first.unwrap_or(true),
                 show_hidden: session.show_hidden,
                 preview: session.preview.unwrap_or(false),
                 split,
@@ -172,6 +222,14 @@ impl State {
             c_memo: Vec::new(),
             p_memo: Vec::new(),
             keyword: None,
+            filter: None,
+            search_mode: config.search_mode.unwrap_or_default(),
+            tabs: vec![Tab::default()],
+            tab_index: 0,
+            pane: None,
+            dir_views: session.dirs.unwrap_or_default(),
+            global_view: None,
+            owner_names: OwnerNames::default(),
         })
     }
 
@@ -214,6 +272,16 @@ impl State {
         }
     }
 
+    /// Open the file at the line in the default command, by `+<line>` as vim, emacs and nano.
+    pub fn open_file_at(&self, path: &Path, line: usize) -> Result<ExitStatus, FxError> {
+        info!("OPEN: {:?} +{}", path, line);
+        Command::new(&self.default)
+            .arg(format!("+{}", line))
+            .arg(path)
+            .status()
+            .or(Err(FxError::OpenItem))
+    }
+
     #[cfg(any(target_os = "linux", target_os = "netbsd"))]
     /// Open the selected file in a new window, according to the config.
     pub fn open_file_in_new_window(&self) -> Result<(), FxError> {
@@ -299,378 +367,936 @@ impl State {
     /// Move items from the current directory to trash directory.
     /// This does not actually delete items.
     /// If you'd like to delete, use `:empty` after this, or just `:rm`.  
+    /// New deletion runs as a job; the items are yanked when it is finished.
     pub fn remove_and_yank(&mut self, targets: &[ItemInfo], new_op: bool) -> Result<(), FxError> {
+        let mut tasks = Vec::new();
+        for item in targets.iter() {
+            //Broken symlinks are just removed.
+            let to = if !new_op || (item.file_type == FileType::Symlink && !item.file_path.exists())
+            {
+                None
+            } else {
+                Some(self.to_trash_path(&item.file_path, &item.file_name)?)
+            };
+            tasks.push(Task {
+                from: item.file_path.clone(),
+                to,
+                remove: true,
+                backup: None,
+            });
+        }
+
+        if new_op {
+            self.jobs.spawn(
+                JobKind::Delete {
+                    original: targets.to_vec(),
+                    dir: self.current_dir.clone(),
+                    registered: self.registered.clone(),
+                    cut: self.cut,
+                },
+                tasks,
+            );
+            Ok(())
+        } else {
+            run_tasks(&tasks, &Progress::default()).1
+        }
+    }
+
+    /// Path in the trash to move the item to.
+    fn to_trash_path(&self, item: &Path, name: &str) -> Result<PathBuf, FxError> {
+        trash::to_trash_path(item, name, &self.trash_dir, self.xdg_trash)
+    }
+
+    /// Register removed items to the registry.
+    fn push_to_registered(&mut self, item: &ItemInfo, file_path: PathBuf, file_name: String) {
+        let mut buf = item.clone();
+        buf.file_path = file_path;
+        buf.file_name = file_name;
+        buf.selected = false;
+        self.registered.push(buf);
+    }
+
+    /// Register selected items to the registry.
+    pub fn yank_item(&mut self, selected: bool) {
         self.registered.clear();
-        let total_selected = targets.len();
-        let mut trash_vec = Vec::new();
-        for (i, item) in targets.iter().enumerate() {
+        self.cut = false;
+        if selected {
+            for item in self.list.iter_mut().filter(|item| item.selected) {
+                self.registered.push(item.clone());
+            }
+        } else if let Ok(item) = self.get_item() {
             let item = item.clone();
+            self.registered.push(item);
+        }
+    }
 
-            delete_cursor();
-            to_info_line();
-            clear_current_line();
-            print!("{}", display_count(i, total_selected));
-
-            match item.file_type {
-                FileType::Directory => match self.remove_and_yank_dir(item.clone(), new_op) {
-                    Err(e) => {
-                        return Err(e);
+    /// Register selected items to the registry, to move them by put.
+    pub fn cut_item(&mut self, selected: bool) {
+        self.yank_item(selected);
+        self.cut = true;
+    }
+
+    /// Move cut items to the current directory or target directory, as a job.
+    /// Items already in the directory are left as they are.
+    /// On the name conflict, it asks how to resolve it in the same way as put.
+    /// The operation is pushed when the job is finished.
+    pub fn move_items(
+        &mut self,
+        targets: &[ItemInfo],
+        target_dir: Option<PathBuf>,
+    ) -> Result<(), FxError> {
+        let dir = target_dir.unwrap_or_else(|| self.current_dir.clone());
+        let mut name_set = BTreeSet::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            name_set.insert(
+                entry
+                    .file_name()
+                    .into_string()
+                    .unwrap_or_else(|_| "".to_string()),
+            );
+        }
+
+        let mut original = Vec::new();
+        let mut tasks = Vec::new();
+        //Policy chosen to apply to all the conflicts.
+        let mut policy_for_all = None;
+        for item in targets.iter() {
+            if item.file_path.parent() == Some(&dir) {
+                continue;
+            }
+            //Cannot move a directory into itself.
+            if dir.starts_with(&item.file_path) {
+                return Err(FxError::PutItem(item.file_path.clone()));
+            }
+            let name = &item.file_name;
+            let policy = if name_set.contains(name) {
+                match policy_for_all {
+                    Some(policy) => Some(policy),
+                    None => {
+                        let (policy, apply_to_all) = match self.ask_conflict(name) {
+                            Ok(answer) => answer,
+                            Err(e) => {
+                                discard_backups(&tasks);
+                                return Err(e);
+                            }
+                        };
+                        if apply_to_all {
+                            policy_for_all = Some(policy);
+                        }
+                        Some(policy)
                     }
-                    Ok(path) => trash_vec.push(path),
-                },
-                FileType::File | FileType::Symlink => {
-                    match self.remove_and_yank_file(item.clone(), new_op) {
+                }
+            } else {
+                None
+            };
+
+            let to = dir.join(name);
+            let (to, backup) = match policy {
+                Some(ConflictPolicy::Skip) => continue,
+                Some(ConflictPolicy::OverwriteIfNewer) if !is_newer(&item.file_path, &to) => {
+                    continue
+                }
+                Some(ConflictPolicy::Overwrite) | Some(ConflictPolicy::OverwriteIfNewer) => {
+                    let backup = match self.to_trash_path(&to, name) {
+                        Ok(backup) => backup,
                         Err(e) => {
+                            discard_backups(&tasks);
                             return Err(e);
                         }
-                        Ok(path) => {
-                            if let Some(p) = path {
-                                trash_vec.push(p);
-                            }
-                        }
-                    }
+                    };
+                    (to, Some(backup))
                 }
-            }
-        }
-        if new_op && !trash_vec.is_empty() {
-            self.operations.branch();
-            //push deleted item information to operations
-            self.operations.push(OpKind::Delete(DeletedFiles {
-                trash: trash_vec,
-                original: targets.to_vec(),
-                dir: self.current_dir.clone(),
-            }));
+                Some(ConflictPolicy::Rename) | None => {
+                    let rename = match item.file_type {
+                        FileType::Directory => rename_dir(name, &name_set),
+                        FileType::File | FileType::Symlink => rename_file(name, &name_set),
+                    };
+                    name_set.insert(rename.clone());
+                    (dir.join(rename), None)
+                }
+            };
+            tasks.push(Task {
+                from: item.file_path.clone(),
+                to: Some(to),
+                remove: true,
+                backup,
+            });
+            original.push(item.file_path.clone());
         }
 
+        if !tasks.is_empty() {
+            self.jobs.spawn(JobKind::Move { original, dir }, tasks);
+        }
         Ok(())
     }
 
-    /// Move single file to trash directory.
-    fn remove_and_yank_file(
+    /// Put items in registry to the current directory or target directory.
+    /// Target directory is used by undo/redo, and in the dual-pane mode.
+    /// New put runs as a job, and the operation is pushed when it is finished.
+    /// On the name conflict, new put asks how to resolve it; otherwise the item is renamed.
+    pub fn put_items(
         &mut self,
-        item: ItemInfo,
+        targets: &[ItemInfo],
+        target_dir: Option<PathBuf>,
         new_op: bool,
-    ) -> Result<Option<PathBuf>, FxError> {
-        //prepare from and to for copy
-        let from = &item.file_path;
-        let mut to = PathBuf::new();
-
-        if item.file_type == FileType::Symlink && !from.exists() {
-            match std::fs::remove_file(from) {
-                Ok(_) => Ok(None),
-                Err(_) => Err(FxError::RemoveItem(from.to_owned())),
-            }
-        } else {
-            let name = &item.file_name;
-            let mut rename = Local::now().timestamp().to_string();
-            rename.push('_');
-            rename.push_str(name);
+    ) -> Result<(), FxError> {
+        let dir = target_dir.unwrap_or_else(|| self.current_dir.clone());
 
-            if new_op {
-                to = self.trash_dir.join(&rename);
+        //make HashSet<String> of file_name
+        let mut name_set = BTreeSet::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            name_set.insert(
+                entry
+                    .file_name()
+                    .into_string()
+                    .unwrap_or_else(|_| "".to_string()),
+            );
+        }
 
-                //copy
-                if std::fs::copy(from, &to).is_err() {
-                    return Err(FxError::PutItem(from.to_owned()));
+        let mut tasks = Vec::new();
+        let mut original = Vec::new();
+        let mut policies = Vec::new();
+        //Policy chosen to apply to all the conflicts.
+        let mut policy_for_all = None;
+        for item in targets.iter() {
+            //Items in the trash are put with the original name.
+            let name = trash::original_name(&item.file_path, &self.trash_dir, self.xdg_trash);
+
+            //An item put in its own directory is always renamed.
+            let policy = if new_op && name_set.contains(&name) && dir.join(&name) != item.file_path
+            {
+                match policy_for_all {
+                    Some(policy) => Some(policy),
+                    None => {
+                        let (policy, apply_to_all) = match self.ask_conflict(&name) {
+                            Ok(answer) => answer,
+                            Err(e) => {
+                                discard_backups(&tasks);
+                                return Err(e);
+                            }
+                        };
+                        if apply_to_all {
+                            policy_for_all = Some(policy);
+                        }
+                        Some(policy)
+                    }
                 }
+            } else {
+                None
+            };
 
-                self.push_to_registered(&item, to.clone(), rename);
-            }
+            let to = dir.join(&name);
+            let (to, backup) = match policy {
+                Some(ConflictPolicy::Skip) => continue,
+                Some(ConflictPolicy::OverwriteIfNewer) if !is_newer(&item.file_path, &to) => {
+                    continue
+                }
+                Some(ConflictPolicy::Overwrite) | Some(ConflictPolicy::OverwriteIfNewer) => {
+                    let backup = match self.to_trash_path(&to, &name) {
+                        Ok(backup) => backup,
+                        Err(e) => {
+                            discard_backups(&tasks);
+                            return Err(e);
+                        }
+                    };
+                    (to, Some(backup))
+                }
+                Some(ConflictPolicy::Rename) | None => {
+                    let rename = match item.file_type {
+                        FileType::Directory => rename_dir(&name, &name_set),
+                        FileType::File | FileType::Symlink => rename_file(&name, &name_set),
+                    };
+                    name_set.insert(rename.clone());
+                    (dir.join(rename), None)
+                }
+            };
+            tasks.push(Task {
+                from: item.file_path.clone(),
+                to: Some(to),
+                remove: false,
+                backup,
+            });
+            original.push(item.clone());
+            policies.push(policy);
+        }
 
-            //remove original
-            if std::fs::remove_file(from).is_err() {
-                return Err(FxError::RemoveItem(from.to_owned()));
+        if new_op {
+            if tasks.is_empty() {
+                return Ok(());
             }
-
-            Ok(Some(to))
+            self.jobs.spawn(
+                JobKind::Put {
+                    original,
+                    dir,
+                    policy: policies,
+                },
+                tasks,
+            );
+            Ok(())
+        } else {
+            run_tasks(&tasks, &Progress::default()).1
         }
     }
 
-    /// Move single directory recursively to trash directory.
-    fn remove_and_yank_dir(&mut self, item: ItemInfo, new_op: bool) -> Result<PathBuf, FxError> {
-        let mut trash_name = String::new();
-        let mut base: usize = 0;
-        let mut trash_path: std::path::PathBuf = PathBuf::new();
-        let mut target: PathBuf;
+    /// Ask how to resolve the name conflict on put.
+    /// Upper case applies the policy to all the remaining conflicts, and Esc cancels the put.
+    fn ask_conflict(&self, name: &str) -> Result<(ConflictPolicy, bool), FxError> {
+        print_warning(
+            format!(
+                "{} exists: o:overwrite s:skip r:rename n:overwrite if newer (O/S/R/N: apply to all)",
+                name
+            ),
+            self.layout.y,
+        );
+        std::io::stdout().flush()?;
 
-        if new_op {
-            let len = walkdir::WalkDir::new(&item.file_path).into_iter().count();
-            let unit = len / 5;
-            for (i, entry) in walkdir::WalkDir::new(&item.file_path)
-                .into_iter()
-                .enumerate()
-            {
-                if i > unit * 4 {
-                    print_process("[»»»»-]");
-                } else if i > unit * 3 {
-                    print_process("[»»»--]");
-                } else if i > unit * 2 {
-                    print_process("[»»---]");
-                } else if i > unit {
-                    print_process("[»----]");
-                } else if i == 0 {
-                    print_process(" [-----]");
-                }
-                let entry = entry?;
-                let entry_path = entry.path();
-                if i == 0 {
-                    base = entry_path.iter().count();
-
-                    trash_name = chrono::Local::now().timestamp().to_string();
-                    trash_name.push('_');
-                    let file_name = entry.file_name().to_str();
-                    if file_name.is_none() {
-                        return Err(FxError::Encode);
+        loop {
+            if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+                match code {
+                    KeyCode::Char(c) => {
+                        let policy = match c.to_ascii_lowercase() {
+                            'o' => ConflictPolicy::Overwrite,
+                            's' => ConflictPolicy::Skip,
+                            'r' => ConflictPolicy::Rename,
+                            'n' => ConflictPolicy::OverwriteIfNewer,
+                            _ => continue,
+                        };
+                        go_to_and_rest_info();
+                        return Ok((policy, c.is_ascii_uppercase()));
                     }
-                    trash_name.push_str(file_name.unwrap());
-                    trash_path = self.trash_dir.join(&trash_name);
-                    std::fs::create_dir(self.trash_dir.join(&trash_path))?;
+                    KeyCode::Esc => {
+                        go_to_and_rest_info();
+                        return Err(FxError::Cancelled);
+                    }
+                    _ => continue,
+                }
+            }
+        }
+    }
 
-                    continue;
-                } else {
-                    if entry.file_type().is_symlink() && !entry_path.exists() {
-                        if std::fs::remove_file(entry_path).is_err() {
-                            return Err(FxError::RemoveItem(entry_path.to_owned()));
-                        }
-                        continue;
+    /// Apply the results of finished jobs: push the operations and register deleted items.
+    pub fn finish_jobs(&mut self) -> Vec<Finished> {
+        let finished = self.jobs.collect();
+        for job in finished.iter() {
+            //Tasks not done leave the names reserved in the XDG trash.
+            for task in job.rest.iter() {
+                match job.kind {
+                    JobKind::Put { .. } | JobKind::Move { .. } => {
+                        task.backup.iter().for_each(|p| trash::discard_info(p))
                     }
-                    target = entry_path.iter().skip(base).collect();
-                    target = trash_path.join(target);
-                    if entry.file_type().is_dir() {
-                        std::fs::create_dir_all(&target)?;
+                    JobKind::Delete { .. } => task.to.iter().for_each(|p| trash::discard_info(p)),
+                    JobKind::Unpack { .. } | JobKind::Command { .. } => {}
+                }
+            }
+            match &job.kind {
+                JobKind::Put {
+                    original,
+                    dir,
+                    policy,
+                } => {
+                    if job.done.is_empty() {
                         continue;
                     }
-
-                    if let Some(parent) = entry_path.parent() {
-                        if !parent.exists() {
-                            std::fs::create_dir(parent)?;
+                    self.operations.branch();
+                    //push put item information to operations
+                    self.operations.push(OpKind::Put(PutFiles {
+                        original: original[..job.done.len()].to_vec(),
+                        put: job.done.iter().filter_map(|task| task.to.clone()).collect(),
+                        dir: dir.clone(),
+                        policy: policy[..job.done.len()].to_vec(),
+                        backup: job.done.iter().map(|task| task.backup.clone()).collect(),
+                    }));
+                }
+                JobKind::Delete {
+                    original,
+                    dir,
+                    registered,
+                    cut,
+                } => {
+                    //Keep items yanked or cut while the job was running.
+                    let yank = self.registered == *registered && self.cut == *cut;
+                    if yank {
+                        self.registered.clear();
+                        self.cut = false;
+                    }
+                    let mut trash_vec = Vec::new();
+                    for (item, task) in original.iter().zip(job.done.iter()) {
+                        if let Some(to) = &task.to {
+                            if yank {
+                                let trash_name = to
+                                    .file_name()
+                                    .map(|name| name.to_string_lossy().to_string())
+                                    .unwrap_or_default();
+                                self.push_to_registered(item, to.clone(), trash_name);
+                            }
+                            trash_vec.push(to.clone());
                         }
                     }
-
-                    if std::fs::copy(entry_path, &target).is_err() {
-                        return Err(FxError::PutItem(entry_path.to_owned()));
+                    if trash_vec.is_empty() {
+                        continue;
                     }
+                    self.operations.branch();
+                    //push deleted item information to operations
+                    self.operations.push(OpKind::Delete(DeletedFiles {
+                        trash: trash_vec,
+                        original: original[..job.done.len()].to_vec(),
+                        dir: dir.clone(),
+                    }));
                 }
+                JobKind::Move { original, .. } => {
+                    if job.done.is_empty() {
+                        continue;
+                    }
+                    self.operations.branch();
+                    //push moved item information to operations
+                    self.operations.push(OpKind::Move(MovedFiles {
+                        original: original[..job.done.len()].to_vec(),
+                        moved: job.done.iter().filter_map(|task| task.to.clone()).collect(),
+                        backup: job.done.iter().map(|task| task.backup.clone()).collect(),
+                    }));
+                }
+                JobKind::Unpack { .. } | JobKind::Command { .. } => {}
             }
-
-            self.push_to_registered(&item, trash_path.clone(), trash_name);
         }
+        finished
+    }
 
-        //remove original
-        if std::fs::remove_dir_all(&item.file_path).is_err() {
-            return Err(FxError::RemoveItem(item.file_path));
+    /// Check the running jobs: reload when any of them is finished, or show the progress.
+    pub fn update_jobs(&mut self) -> Result<(), FxError> {
+        let finished = self.finish_jobs();
+        match finished.last() {
+            None => {
+                if let Some(detail) = self.jobs.detail() {
+                    print_process(detail);
+                }
+                self.print_job_status();
+                move_to(self.layout.x_offset + 1, self.layout.y);
+            }
+            Some(job) => {
+                self.reload_and_fit_cursor()?;
+                if job.is_error() {
+                    print_warning(job.message(), self.layout.y);
+                } else {
+                    print_info(job.message(), self.layout.y);
+                }
+            }
         }
-
-        Ok(trash_path)
+        Ok(())
     }
 
-    /// Register removed items to the registry.
-    fn push_to_registered(&mut self, item: &ItemInfo, file_path: PathBuf, file_name: String) {
-        let mut buf = item.clone();
-        buf.file_path = file_path;
-        buf.file_name = file_name;
-        buf.selected = false;
-        self.registered.push(buf);
+    /// Reload the list, moving the cursor up if the items below it are gone.
+    pub fn reload_and_fit_cursor(&mut self) -> Result<(), FxError> {
+        self.update_list()?;
+        let new_len = self.list.len();
+        if new_len == 0 {
+            self.layout.nums.reset();
+            self.layout.y = BEGINNING_ROW;
+        } else if self.layout.nums.index > new_len - 1 {
+            let diff = (self.layout.nums.index - (new_len - 1)) as u16;
+            self.layout.y = if self.layout.y - diff < BEGINNING_ROW {
+                BEGINNING_ROW
+            } else {
+                self.layout.y - diff
+            };
+            self.layout.nums.index = new_len - 1;
+            if usize::from(self.layout.nums.skip) > self.layout.nums.index {
+                self.layout.nums.skip = self.layout.nums.index as u16;
+            }
+        }
+        self.reload(self.layout.y)
     }
 
-    /// Register selected items to the registry.
-    pub fn yank_item(&mut self, selected: bool) {
-        self.registered.clear();
-        if selected {
-            for item in self.list.iter_mut().filter(|item| item.selected) {
-                self.registered.push(item.clone());
+    /// Print the progress of running jobs at the right end of the status line.
+    fn print_job_status(&self) {
+        if let Some(summary) = self.jobs.summary() {
+            let width = summary.chars().count() as u16;
+            if width >= self.layout.terminal_column {
+                return;
             }
-        } else if let Ok(item) = self.get_item() {
-            let item = item.clone();
-            self.registered.push(item);
+            move_to(
+                self.layout.x_offset + self.layout.terminal_column - width + 1,
+                self.layout.terminal_row,
+            );
+            print!("{}", summary.negative());
         }
     }
 
-    /// Put items in registry to the current directory or target directory.
-    /// Only Redo command uses target directory.
-    pub fn put_items(
-        &mut self,
-        targets: &[ItemInfo],
-        target_dir: Option<PathBuf>,
-    ) -> Result<(), FxError> {
-        //make HashSet<String> of file_name
-        let mut name_set = BTreeSet::new();
-        match &target_dir {
-            None => {
-                for item in self.list.iter() {
-                    name_set.insert(item.file_name.clone());
-                }
+    /// Show the jobs view, where `d` cancels the job under the cursor.
+    pub fn show_jobs(&mut self, mut screen: &Stdout) -> Result<(), FxError> {
+        let mut index = 0;
+        loop {
+            //Jobs finished meanwhile are applied as usual.
+            self.finish_jobs();
+            self.print_jobs(index)?;
+            screen.flush()?;
+
+            if !crossterm::event::poll(JOB_POLL_INTERVAL)? {
+                continue;
             }
-            Some(path) => {
-                for entry in std::fs::read_dir(path)? {
-                    let entry = entry?;
-                    name_set.insert(
-                        entry
-                            .file_name()
-                            .into_string()
-                            .unwrap_or_else(|_| "".to_string()),
-                    );
+            if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+                match code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        if index + 1 < self.jobs.list.len() {
+                            index += 1;
+                        }
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        index = index.saturating_sub(1);
+                    }
+                    KeyCode::Char('d') => {
+                        self.jobs.cancel(index);
+                    }
+                    _ => {
+                        break;
+                    }
                 }
             }
         }
+        Ok(())
+    }
 
-        //prepare for operations.push
-        let mut put_v = Vec::new();
+    fn print_jobs(&self, index: usize) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        move_to(1, 1);
+        print!(
+            "{}",
+            split_str(" Jobs (d: cancel, other keys: return)", width as usize).bold()
+        );
+        if self.jobs.list.is_empty() {
+            move_to(3, BEGINNING_ROW);
+            print!("No jobs.");
+            return Ok(());
+        }
 
-        let total_selected = targets.len();
-        for (i, item) in targets.iter().enumerate() {
-            delete_cursor();
-            to_info_line();
-            clear_current_line();
-            print!("{}", display_count(i, total_selected));
-
-            match item.file_type {
-                FileType::Directory => {
-                    if let Ok(p) = self.put_dir(item, &target_dir, &mut name_set) {
-                        put_v.push(p);
+        let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+        let skip = (index + 1).saturating_sub(rows);
+        for (i, job) in self.jobs.list.iter().enumerate().skip(skip).take(rows) {
+            let line = split_str(&job.to_line(), width.saturating_sub(3) as usize);
+            move_to(1, (i - skip) as u16 + BEGINNING_ROW);
+            if i == index {
+                print!("> {}", line.negative());
+            } else {
+                print!("  {}", line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the captured output of the command in the scrollable pager.
+    pub fn show_output(&self, mut screen: &Stdout, title: &str, text: &str) -> Result<(), FxError> {
+        let lines: Vec<String> = text
+            .lines()
+            .map(|line| {
+                line.replace('\t', "    ")
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .collect()
+            })
+            .collect();
+        let mut top = 0;
+        loop {
+            let (_, height) = terminal_size()?;
+            let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+            let max_top = lines.len().saturating_sub(rows);
+            top = top.min(max_top);
+            self.print_output(title, &lines, top)?;
+            screen.flush()?;
+
+            if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+                match code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        top = (top + 1).min(max_top);
                     }
-                }
-                FileType::File | FileType::Symlink => {
-                    if let Ok(q) = self.put_file(item, &target_dir, &mut name_set) {
-                        put_v.push(q);
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        top = top.saturating_sub(1);
+                    }
+                    KeyCode::Char('f') | KeyCode::Char(' ') | KeyCode::PageDown => {
+                        top = (top + rows).min(max_top);
+                    }
+                    KeyCode::Char('b') | KeyCode::PageUp => {
+                        top = top.saturating_sub(rows);
+                    }
+                    KeyCode::Char('g') | KeyCode::Home => {
+                        top = 0;
+                    }
+                    KeyCode::Char('G') | KeyCode::End => {
+                        top = max_top;
+                    }
+                    _ => {
+                        break;
                     }
                 }
             }
         }
-        if target_dir.is_none() {
-            self.operations.branch();
-            //push put item information to operations
-            self.operations.push(OpKind::Put(PutFiles {
-                original: targets.to_owned(),
-                put: put_v,
-                dir: self.current_dir.clone(),
-            }));
+        Ok(())
+    }
+
+    fn print_output(&self, title: &str, lines: &[String], top: usize) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        move_to(1, 1);
+        print!(
+            "{}",
+            split_str(
+                &format!(
+                    " {} (j/k: scroll, f/b: page, g/G: top/bottom, other keys: return)",
+                    title
+                ),
+                width as usize
+            )
+            .bold()
+        );
+        if lines.is_empty() {
+            move_to(3, BEGINNING_ROW);
+            print!("No output.");
+            return Ok(());
         }
 
+        let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+        for (i, line) in lines.iter().skip(top).take(rows).enumerate() {
+            move_to(1, i as u16 + BEGINNING_ROW);
+            print!("{}", split_str(line, width as usize));
+        }
         Ok(())
     }
 
-    /// Put single item to current or target directory.
-    fn put_file(
-        &mut self,
-        item: &ItemInfo,
-        target_dir: &Option<PathBuf>,
-        name_set: &mut BTreeSet<String>,
-    ) -> Result<PathBuf, FxError> {
-        match target_dir {
-            None => {
-                if item.file_path.parent() == Some(&self.trash_dir) {
-                    let rename: String = item.file_name.chars().skip(TIME_PREFIX).collect();
-                    let rename = rename_file(&rename, name_set);
-                    let to = &self.current_dir.join(&rename);
-                    if std::fs::copy(&item.file_path, to).is_err() {
-                        return Err(FxError::PutItem(item.file_path.clone()));
+    /// Show the trash view with the original name, path and deletion time of the trashed items.
+    pub fn show_trash(&mut self, mut screen: &Stdout) -> Result<(), FxError> {
+        let mut items = trash::list_trash(&self.trash_dir, self.xdg_trash)?;
+        let mut index = 0;
+        let mut message = String::new();
+        loop {
+            self.print_trash(&items, index, &message)?;
+            screen.flush()?;
+            message.clear();
+
+            if let Event::Key(KeyEvent { code, .. }) = crossterm::event::read()? {
+                match code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        if index + 1 < items.len() {
+                            index += 1;
+                        }
                     }
-                    name_set.insert(rename);
-                    Ok(to.to_path_buf())
-                } else {
-                    let rename = rename_file(&item.file_name, name_set);
-                    let to = &self.current_dir.join(&rename);
-                    if std::fs::copy(&item.file_path, to).is_err() {
-                        return Err(FxError::PutItem(item.file_path.clone()));
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        index = index.saturating_sub(1);
                     }
-                    name_set.insert(rename);
-                    Ok(to.to_path_buf())
-                }
-            }
-            Some(path) => {
-                if item.file_path.parent() == Some(&self.trash_dir) {
-                    let rename: String = item.file_name.chars().skip(TIME_PREFIX).collect();
-                    let rename = rename_file(&rename, name_set);
-                    let to = path.join(&rename);
-                    if std::fs::copy(&item.file_path, to.clone()).is_err() {
-                        return Err(FxError::PutItem(item.file_path.clone()));
+                    KeyCode::Char('r') | KeyCode::Char('p') => {
+                        let item = match items.get(index) {
+                            Some(item) => item,
+                            None => continue,
+                        };
+                        //r restores to the original directory, p to the current one.
+                        let dir = if code == KeyCode::Char('r') {
+                            match &item.original_dir {
+                                Some(dir) => dir.clone(),
+                                None => {
+                                    message =
+                                        "Original path unknown: Use p to restore here.".to_owned();
+                                    continue;
+                                }
+                            }
+                        } else {
+                            self.current_dir.clone()
+                        };
+                        match trash::restore(item, &dir) {
+                            Ok(restored) => {
+                                message = format!("Restored to {}", restored.display());
+                                items.remove(index);
+                            }
+                            Err(e) => message = e.to_string(),
+                        }
                     }
-                    name_set.insert(rename);
-                    Ok(to)
-                } else {
-                    let rename = rename_file(&item.file_name, name_set);
-                    let to = &path.join(&rename);
-                    if std::fs::copy(&item.file_path, to).is_err() {
-                        return Err(FxError::PutItem(item.file_path.clone()));
+                    KeyCode::Char('D') => {
+                        let item = match items.get(index) {
+                            Some(item) => item,
+                            None => continue,
+                        };
+                        self.print_trash(
+                            &items,
+                            index,
+                            &format!("Delete {} permanently? (y/n)", item.name),
+                        )?;
+                        screen.flush()?;
+                        if let Event::Key(KeyEvent {
+                            code: KeyCode::Char('y'),
+                            ..
+                        }) = crossterm::event::read()?
+                        {
+                            match trash::delete_permanently(item) {
+                                Ok(_) => {
+                                    message = format!("Deleted {}", item.name);
+                                    items.remove(index);
+                                }
+                                Err(e) => message = e.to_string(),
+                            }
+                        }
+                    }
+                    _ => {
+                        break;
                     }
-                    name_set.insert(rename);
-                    Ok(to.to_path_buf())
+                }
+                if index >= items.len() {
+                    index = items.len().saturating_sub(1);
                 }
             }
         }
+        Ok(())
     }
 
-    /// Put single directory recursively to current or target directory.
-    fn put_dir(
-        &mut self,
-        buf: &ItemInfo,
-        target_dir: &Option<PathBuf>,
-        name_set: &mut BTreeSet<String>,
-    ) -> Result<PathBuf, FxError> {
-        let mut base: usize = 0;
-        let mut target: PathBuf = PathBuf::new();
-        let original_path = &buf.file_path;
-
-        let len = walkdir::WalkDir::new(original_path).into_iter().count();
-        let unit = len / 5;
-        for (i, entry) in walkdir::WalkDir::new(original_path).into_iter().enumerate() {
-            if i > unit * 4 {
-                print_process("[»»»»-]");
-            } else if i > unit * 3 {
-                print_process("[»»»--]");
-            } else if i > unit * 2 {
-                print_process("[»»---]");
-            } else if i > unit {
-                print_process("[»----]");
-            } else if i == 0 {
-                print_process(" [»----]");
-            }
-            let entry = entry?;
-            let entry_path = entry.path();
-            if i == 0 {
-                base = entry_path.iter().count();
-
-                if original_path.parent() == Some(&self.trash_dir) {
-                    let rename: String = buf.file_name.chars().skip(TIME_PREFIX).collect();
-                    target = match &target_dir {
-                        None => self.current_dir.join(&rename),
-                        Some(path) => path.join(&rename),
-                    };
-                    let rename = rename_dir(&rename, name_set);
-                    name_set.insert(rename);
-                } else {
-                    let rename = rename_dir(&buf.file_name, name_set);
-                    target = match &target_dir {
-                        None => self.current_dir.join(&rename),
-                        Some(path) => path.join(&rename),
-                    };
-                    name_set.insert(rename);
-                }
-                std::fs::create_dir(&target)?;
-                continue;
+    fn print_trash(
+        &self,
+        items: &[trash::TrashItem],
+        index: usize,
+        message: &str,
+    ) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        move_to(1, 1);
+        print!(
+            "{}",
+            split_str(
+                " Trash (r: restore, p: restore here, D: delete permanently, other keys: return)",
+                width as usize
+            )
+            .bold()
+        );
+        if !message.is_empty() {
+            move_to(1, height);
+            print!("{}", split_str(message, width as usize));
+        }
+        if items.is_empty() {
+            move_to(3, BEGINNING_ROW);
+            print!("Trash is empty.");
+            return Ok(());
+        }
+
+        let name_width = items
+            .iter()
+            .map(|item| item.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(width as usize / 3);
+        let rows = height.saturating_sub(BEGINNING_ROW + 1) as usize;
+        let skip = (index + 1).saturating_sub(rows);
+        for (i, item) in items.iter().enumerate().skip(skip).take(rows) {
+            let line = format!(
+                "{:<name_width$} {:<16} {}",
+                split_str(&item.name, name_width),
+                item.deleted.as_deref().unwrap_or("-"),
+                item.original_dir
+                    .as_ref()
+                    .map_or("-".to_owned(), |dir| dir.display().to_string()),
+                name_width = name_width
+            );
+            let line = split_str(&line, width.saturating_sub(3) as usize);
+            move_to(1, (i - skip) as u16 + BEGINNING_ROW);
+            if i == index {
+                print!("> {}", line.negative());
             } else {
-                let child: PathBuf = entry_path.iter().skip(base).collect();
-                let child = target.join(child);
-
-                if entry.file_type().is_dir() {
-                    std::fs::create_dir_all(child)?;
-                    continue;
-                } else if let Some(parent) = entry_path.parent() {
-                    if !parent.exists() {
-                        std::fs::create_dir(parent)?;
+                print!("  {}", line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename items at once by editing their names in the editor, one name per line.
+    /// Returns the number of renamed items.
+    pub fn bulk_rename(&mut self, targets: &[ItemInfo]) -> Result<usize, FxError> {
+        let names: Vec<String> = targets.iter().map(|item| item.file_name.clone()).collect();
+        if names.iter().any(|name| name.contains('\n')) {
+            return Err(FxError::Rename(
+                "Cannot rename names containing a newline at once.".to_owned(),
+            ));
+        }
+
+        let mut content = names.join("\n");
+        content.push('\n');
+        let (temp_dir, temp) = create_private_temp_file("felix_bulk_rename", &content)?;
+        info!("BULK RENAME: {:?}", temp);
+        let status = Command::new(&self.default).arg(&temp).status();
+        let edited = fs::read_to_string(&temp);
+        let _ = fs::remove_dir_all(&temp_dir);
+        if !status.or(Err(FxError::OpenItem))?.success() {
+            return Err(FxError::Cancelled);
+        }
+
+        let mut existing = BTreeSet::new();
+        for entry in fs::read_dir(&self.current_dir)? {
+            existing.insert(entry?.file_name().to_string_lossy().to_string());
+        }
+        let changed = diff_names(&names, &edited?, &existing)?;
+        self.apply_renames(targets, &changed)
+    }
+
+    /// Rename items by the substitution or the template of `:rename`, after the preview and confirmation.
+    /// Returns the number of renamed items.
+    pub fn rename_by_pattern(
+        &mut self,
+        targets: &[ItemInfo],
+        pattern: &str,
+        screen: &Stdout,
+    ) -> Result<usize, FxError> {
+        let names: Vec<String> = targets.iter().map(|item| item.file_name.clone()).collect();
+        let new_names = Renamer::new(pattern)?.new_names(targets);
+
+        //Show the before/after names in the preview area, or in the full screen without the preview.
+        let lines: Vec<String> = names
+            .iter()
+            .zip(new_names.iter())
+            .filter(|(name, new_name)| name != new_name)
+            .map(|(name, new_name)| format!("{} -> {}", name, new_name))
+            .collect();
+        let full_screen = !self.layout.preview;
+        if full_screen {
+            self.print_rename_list(&lines)?;
+        } else {
+            self.layout.print_rename_preview(&lines);
+        }
+
+        let result = self.confirm_renames(targets, &names, &new_names, screen);
+        //Back to the item list, as the caller reloads only after renaming.
+        if full_screen && result.is_err() {
+            self.redraw(self.layout.y);
+        }
+        result
+    }
+
+    /// Ask whether to rename the items to the new names, and rename them if confirmed.
+    fn confirm_renames(
+        &mut self,
+        targets: &[ItemInfo],
+        names: &[String],
+        new_names: &[String],
+        mut screen: &Stdout,
+    ) -> Result<usize, FxError> {
+        let mut existing = BTreeSet::new();
+        for entry in fs::read_dir(&self.current_dir)? {
+            existing.insert(entry?.file_name().to_string_lossy().to_string());
+        }
+        let changed = changed_names(names, new_names, &existing)?;
+        if changed.is_empty() {
+            return Ok(0);
+        }
+
+        print_warning(
+            format!("Rename {} item(s)? (y/n)", changed.len()),
+            self.layout.y,
+        );
+        screen.flush()?;
+        match crossterm::event::read()? {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('y'),
+                ..
+            }) => self.apply_renames(targets, &changed),
+            _ => Err(FxError::Cancelled),
+        }
+    }
+
+    /// Print the before/after names of `:rename` in the full screen.
+    fn print_rename_list(&self, lines: &[String]) -> Result<(), FxError> {
+        clear_all();
+        let (width, height) = terminal_size()?;
+        move_to(1, 1);
+        print!("{}", split_str(" Rename", width as usize).bold());
+        if lines.is_empty() {
+            move_to(3, BEGINNING_ROW);
+            print!("(No items to be renamed)");
+            return Ok(());
+        }
+
+        let rows = height.saturating_sub(BEGINNING_ROW) as usize;
+        for (i, line) in lines.iter().take(rows).enumerate() {
+            move_to(3, i as u16 + BEGINNING_ROW);
+            print!("{}", split_str(line, width.saturating_sub(3) as usize));
+        }
+        Ok(())
+    }
+
+    /// Rename the items by the index and new name, recorded as one operation.
+    fn apply_renames(
+        &mut self,
+        targets: &[ItemInfo],
+        changed: &[(usize, String)],
+    ) -> Result<usize, FxError> {
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        let pairs: Vec<(PathBuf, PathBuf)> = changed
+            .iter()
+            .map(|(i, new_name)| {
+                let from = targets[*i].file_path.clone();
+                let to = from.with_file_name(new_name);
+                (from, to)
+            })
+            .collect();
+        rename_items(&pairs)?;
+
+        self.operations.branch();
+        let (original, renamed) = pairs.into_iter().unzip();
+        self.operations
+            .push(OpKind::BulkRename(RenamedFiles { original, renamed }));
+        Ok(changed.len())
+    }
+
+    /// Change the mode and the owner of the items as edited in the permissions editor.
+    /// The mode change is pushed to the undo history. Returns the number of the changed items.
+    pub fn change_permissions(
+        &mut self,
+        items: &[ItemInfo],
+        editor: &PermissionsEditor,
+    ) -> Result<usize, FxError> {
+        let mut paths = Vec::new();
+        let mut original = Vec::new();
+        let mut changed = Vec::new();
+        let mut count = 0;
+        let mut result = Ok(());
+        for item in items {
+            //The mode of symlinks is not used.
+            let mode = match item.file_type {
+                FileType::Symlink => None,
+                _ => item.permissions,
+            };
+            if let Some(old) = mode {
+                let new = editor.apply_to(old);
+                if new != old {
+                    if let Err(e) = chmod(&item.file_path, new) {
+                        result = Err(e);
+                        break;
                     }
+                    paths.push(item.file_path.clone());
+                    original.push(old);
+                    changed.push(new);
                 }
-
-                if std::fs::copy(entry_path, &child).is_err() {
-                    return Err(FxError::PutItem(entry_path.to_owned()));
+            }
+            if editor.user.is_some() || editor.group.is_some() {
+                if let Err(e) = chown(
+                    &item.file_path,
+                    editor.user.as_deref(),
+                    editor.group.as_deref(),
+                ) {
+                    result = Err(e);
+                    break;
                 }
             }
+            count += 1;
+        }
+
+        //Modes changed before an error are still recorded, so that they can be undone.
+        if !paths.is_empty() {
+            self.operations.branch();
+            self.operations.push(OpKind::Chmod(ChangedModes {
+                paths,
+                original,
+                changed,
+            }));
         }
-        Ok(target)
+        result.map(|_| count)
     }
 
-    /// Undo operations (put/delete/rename).
+    /// Undo operations (put/delete/rename/move).
     pub fn undo(&mut self, op: &OpKind) -> Result<(), FxError> {
         match op {
             OpKind::Rename(op) => {
@@ -681,12 +1307,27 @@ impl State {
                 self.list_up();
                 print_info("UNDONE: RENAME", BEGINNING_ROW);
             }
+            OpKind::BulkRename(op) => {
+                let pairs: Vec<(PathBuf, PathBuf)> = op
+                    .renamed
+                    .iter()
+                    .cloned()
+                    .zip(op.original.iter().cloned())
+                    .collect();
+                rename_items(&pairs)?;
+                self.operations.pos += 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("UNDONE: BULK RENAME", BEGINNING_ROW);
+            }
             OpKind::Put(op) => {
-                for x in &op.put {
-                    if x.is_dir() {
-                        std::fs::remove_dir_all(x)?;
-                    } else {
-                        std::fs::remove_file(x)?;
+                //Restore the overwritten items in the reverse order.
+                for (x, backup) in op.put.iter().zip(op.backup.iter()).rev() {
+                    remove_item(x)?;
+                    if let Some(backup) = backup {
+                        move_item(backup, x)?;
+                        trash::discard_info(backup);
                     }
                 }
                 self.operations.pos += 1;
@@ -696,20 +1337,47 @@ impl State {
                 print_info("UNDONE: PUT", BEGINNING_ROW);
             }
             OpKind::Delete(op) => {
-                let targets = trash_to_info(&self.trash_dir, &op.trash)?;
-                self.put_items(&targets, Some(op.dir.clone()))?;
+                let targets = trash_to_info(&op.trash);
+                self.put_items(&targets, Some(op.dir.clone()), false)?;
                 self.operations.pos += 1;
                 self.update_list()?;
                 self.clear_and_show_headline();
                 self.list_up();
                 print_info("UNDONE: DELETE", BEGINNING_ROW);
             }
+            OpKind::Move(op) => {
+                //Restore the overwritten items in the reverse order.
+                for (i, (original, moved)) in
+                    op.original.iter().zip(op.moved.iter()).enumerate().rev()
+                {
+                    move_item(moved, original)?;
+                    if let Some(Some(backup)) = op.backup.get(i) {
+                        move_item(backup, moved)?;
+                        trash::discard_info(backup);
+                    }
+                }
+                self.operations.pos += 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("UNDONE: MOVE", BEGINNING_ROW);
+            }
+            OpKind::Chmod(op) => {
+                for (p, mode) in op.paths.iter().zip(op.original.iter()) {
+                    chmod(p, *mode)?;
+                }
+                self.operations.pos += 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("UNDONE: CHMOD", BEGINNING_ROW);
+            }
         }
         relog(op, true);
         Ok(())
     }
 
-    /// Redo operations (put/delete/rename)
+    /// Redo operations (put/delete/rename/move)
     pub fn redo(&mut self, op: &OpKind) -> Result<(), FxError> {
         match op {
             OpKind::Rename(op) => {
@@ -720,8 +1388,34 @@ impl State {
                 self.list_up();
                 print_info("REDONE: RENAME", BEGINNING_ROW);
             }
+            OpKind::BulkRename(op) => {
+                let pairs: Vec<(PathBuf, PathBuf)> = op
+                    .original
+                    .iter()
+                    .cloned()
+                    .zip(op.renamed.iter().cloned())
+                    .collect();
+                rename_items(&pairs)?;
+                self.operations.pos -= 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("REDONE: BULK RENAME", BEGINNING_ROW);
+            }
             OpKind::Put(op) => {
-                self.put_items(&op.original, Some(op.dir.clone()))?;
+                let tasks: Vec<Task> = op
+                    .original
+                    .iter()
+                    .zip(op.put.iter())
+                    .zip(op.backup.iter())
+                    .map(|((item, put), backup)| Task {
+                        from: item.file_path.clone(),
+                        to: Some(put.clone()),
+                        remove: false,
+                        backup: backup.clone(),
+                    })
+                    .collect();
+                run_tasks(&tasks, &Progress::default()).1?;
                 self.operations.pos -= 1;
                 self.update_list()?;
                 self.clear_and_show_headline();
@@ -736,6 +1430,29 @@ impl State {
                 self.list_up();
                 print_info("REDONE DELETE", BEGINNING_ROW);
             }
+            OpKind::Move(op) => {
+                for (i, (original, moved)) in op.original.iter().zip(op.moved.iter()).enumerate() {
+                    if let Some(Some(backup)) = op.backup.get(i) {
+                        move_item(moved, backup)?;
+                    }
+                    move_item(original, moved)?;
+                }
+                self.operations.pos -= 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("REDONE: MOVE", BEGINNING_ROW);
+            }
+            OpKind::Chmod(op) => {
+                for (p, mode) in op.paths.iter().zip(op.changed.iter()) {
+                    chmod(p, *mode)?;
+                }
+                self.operations.pos -= 1;
+                self.update_list()?;
+                self.clear_and_show_headline();
+                self.list_up();
+                print_info("REDONE: CHMOD", BEGINNING_ROW);
+            }
         }
         relog(op, false);
         Ok(())
@@ -751,6 +1468,7 @@ impl State {
     /// Reload the item list and redraw it.
     pub fn reload(&mut self, y: u16) -> Result<(), FxError> {
         self.update_list()?;
+        self.reload_pane()?;
         self.clear_and_show_headline();
         self.list_up();
         self.move_cursor(y);
@@ -759,7 +1477,7 @@ impl State {
 
     /// Reload the app layout when terminal size changes.
     pub fn refresh(&mut self, column: u16, row: u16, mut cursor_pos: u16) -> Result<(), FxError> {
-        let (time_start, name_max) = make_layout(column);
+        let (column_starts, name_max) = make_layout(column, &self.layout.columns);
 
         let (original_column, original_row) = terminal_size()?;
 
@@ -777,7 +1495,7 @@ impl State {
             false => (0, 0),
         };
         self.layout.name_max_len = name_max;
-        self.layout.time_start_pos = time_start;
+        self.layout.column_starts = column_starts;
 
         if cursor_pos > row - 1 {
             self.layout.nums.index -= (cursor_pos - row + 1) as usize;
@@ -788,13 +1506,61 @@ impl State {
         Ok(())
     }
 
+    /// Refresh the layout after the preview is turned on or off.
+    pub fn refresh_preview(&mut self) -> Result<(), FxError> {
+        let (column, row) = terminal_size()?;
+        let (column, row) = match (self.layout.preview, self.layout.split) {
+            (false, _) => (column, row),
+            (true, Split::Vertical) => (column / 2, row),
+            (true, Split::Horizontal) => (column, row / 2),
+        };
+        self.refresh(column, row, self.layout.y)
+    }
+
     /// Clear all and show the current directory information.
     pub fn clear_and_show_headline(&mut self) {
         clear_all();
-        move_to(1, 1);
+        if let Some(pane) = &self.pane {
+            self.print_pane(pane);
+        }
+        move_to(self.layout.x_offset + 1, 1);
 
         let mut header_space = (self.layout.terminal_column - 1) as usize;
 
+        //Show tabs if more than one are opened.
+        if self.tabs.len() > 1 {
+            let labels: Vec<String> = self
+                .tabs
+                .iter()
+                .enumerate()
+                .map(|(i, tab)| {
+                    if i == self.tab_index {
+                        tab_label(i, &self.current_dir)
+                    } else {
+                        tab_label(i, &tab.current_dir)
+                    }
+                })
+                .collect();
+            //If the labels are too long, show only the index of tabs.
+            let labels: Vec<String> =
+                if labels.iter().map(|x| x.len()).sum::<usize>() > header_space / 2 {
+                    (1..=self.tabs.len()).map(|i| format!(" {} ", i)).collect()
+                } else {
+                    labels
+                };
+            for (i, label) in labels.iter().enumerate() {
+                if label.len() >= header_space {
+                    break;
+                }
+                if i == self.tab_index {
+                    print!("{}", label.as_str().negative());
+                } else {
+                    print!("{}", label);
+                }
+                header_space -= label.len();
+            }
+        }
+
         //Show current directory path.
         //crossterm's Stylize cannot be applied to PathBuf,
         //current directory does not have any text attribute for now.
@@ -832,7 +1598,8 @@ impl State {
     }
 
     /// Print an item in the directory.
-    fn print_item(&self, item: &ItemInfo) {
+    /// `x_offset` is the column offset of the pane where the item is printed.
+    fn print_item(&self, item: &ItemInfo, x_offset: u16) {
         let name = if item.file_name.bytes().len() <= self.layout.name_max_len {
             item.file_name.clone()
         } else {
@@ -841,103 +1608,130 @@ impl State {
             file_name.push_str("..");
             file_name
         };
-        let time = format_time(&item.modified);
         let color = match item.file_type {
             FileType::Directory => &self.layout.colors.dir_fg,
             FileType::File => &self.layout.colors.file_fg,
             FileType::Symlink => &self.layout.colors.symlink_fg,
         };
-        if self.layout.terminal_column < PROPER_WIDTH {
-            if item.selected {
-                set_color(&TermColor::ForeGround(color));
-                print!("{}", name.negative(),);
-                reset_color();
-            } else if item.matches {
-                set_color(&TermColor::ForeGround(color));
-                print!("{}", name.bold(),);
-                reset_color();
-            } else {
-                set_color(&TermColor::ForeGround(color));
-                print!("{}", name);
-                reset_color();
-            }
-            if self.layout.terminal_column > self.layout.time_start_pos + TIME_WIDTH {
-                clear_until_newline();
-            }
-        } else if item.selected {
-            set_color(&TermColor::ForeGround(color));
-            print!("{}", name.negative(),);
-            move_left(1000);
-            move_right(self.layout.time_start_pos - 1);
-            print!(" {}", time.negative());
-            reset_color();
+        set_color(&TermColor::ForeGround(color));
+        if item.selected {
+            print!("{}", name.negative());
         } else if item.matches {
-            set_color(&TermColor::ForeGround(color));
-            print!("{}", name.bold(),);
-            move_left(1000);
-            move_right(self.layout.time_start_pos - 1);
-            set_color(&TermColor::ForeGround(color));
-            print!(" {}", time);
-            reset_color();
+            print!("{}", name.bold());
         } else {
-            set_color(&TermColor::ForeGround(color));
             print!("{}", name);
+        }
+        for (column, start) in self.layout.column_starts.iter() {
+            let value = fit_to_width(
+                &column_value(item, *column),
+                column.width() as usize,
+                column.is_numeric(),
+            );
             move_left(1000);
-            move_right(self.layout.time_start_pos - 1);
-            print!(" {}", time);
-            reset_color();
+            move_right(x_offset + start - 1);
+            if item.selected {
+                print!(" {}", value.negative());
+            } else {
+                print!(" {}", value);
+            }
         }
+        reset_color();
     }
 
     /// Print items in the directory.
     pub fn list_up(&self) {
-        let visible = &self.list[..];
-
-        visible.iter().enumerate().for_each(|(index, item)| {
+        self.list.iter().enumerate().for_each(|(index, item)| {
             if index >= self.layout.nums.skip.into()
                 && index < (self.layout.terminal_row + self.layout.nums.skip - BEGINNING_ROW).into()
             {
-                move_to(3, (index as u16 + BEGINNING_ROW) - self.layout.nums.skip);
-                self.print_item(item);
+                move_to(
+                    self.layout.x_offset + 3,
+                    (index as u16 + BEGINNING_ROW) - self.layout.nums.skip,
+                );
+                self.print_item(item, self.layout.x_offset);
             }
         });
     }
 
     /// Update state's list of items.
     pub fn update_list(&mut self) -> Result<(), FxError> {
-        let mut result = Vec::new();
-        let mut dir_v = Vec::new();
-        let mut file_v = Vec::new();
+        self.list = self.read_items(&self.current_dir)?;
+        if let Some(filter) = &self.filter {
+            self.list.retain(|x| filter.is_match(&x.file_name));
+        }
+        Ok(())
+    }
 
-        for entry in fs::read_dir(&self.current_dir)? {
+    /// Set the list to the items matching the filter.
+    pub fn apply_filter(&mut self, items: &[ItemInfo]) {
+        self.list = match &self.filter {
+            Some(filter) => items
+                .iter()
+                .filter(|x| filter.is_match(&x.file_name))
+                .cloned()
+                .collect(),
+            None => items.to_vec(),
+        };
+    }
+
+    /// Read items in the directory, sorted according to the layout.
+    fn read_items(&self, dir: &Path) -> Result<Vec<ItemInfo>, FxError> {
+        let mut result = Vec::new();
+        for entry in fs::read_dir(dir)? {
             let e = entry?;
-            let entry = read_item(e);
-            match entry.file_type {
-                FileType::Directory => dir_v.push(entry),
-                FileType::File | FileType::Symlink => file_v.push(entry),
-            }
+            result.push(read_item(e));
         }
+        self.read_columns(&mut result);
+        self.sort_items(&mut result);
 
-        match self.layout.sort_by {
-            SortKey::Name => {
-                dir_v.sort_by(|a, b| natord::compare_ignore_case(&a.file_name, &b.file_name));
-                file_v.sort_by(|a, b| natord::compare_ignore_case(&a.file_name, &b.file_name));
-            }
-            SortKey::Time => {
-                dir_v.sort_by(|a, b| b.modified.partial_cmp(&a.modified).unwrap());
-                file_v.sort_by(|a, b| b.modified.partial_cmp(&a.modified).unwrap());
-            }
+        if !self.layout.show_hidden {
+            result.retain(|x| !x.is_hidden);
         }
 
-        result.append(&mut dir_v);
-        result.append(&mut file_v);
+        Ok(result)
+    }
 
-        if !self.layout.show_hidden {
-            result.retain(|x| !x.is_hidden);
+    /// Read the owner names and the item counts of directories, if the columns are shown.
+    fn read_columns(&self, items: &mut [ItemInfo]) {
+        if self.layout.columns.contains(&Column::Owner) {
+            for item in items.iter_mut() {
+                if let (Some(uid), Some(gid)) = (item.uid, item.gid) {
+                    let (user, group) = self.owner_names.get(uid, gid);
+                    item.owner = Some(format!("{} {}", user, group));
+                }
+            }
+        }
+        if self.layout.columns.contains(&Column::Count) {
+            for item in items.iter_mut() {
+                if item.file_type == FileType::Directory {
+                    item.dir_count = fs::read_dir(&item.file_path).ok().map(|entries| {
+                        entries
+                            .flatten()
+                            .filter(|e| {
+                                self.layout.show_hidden
+                                    || !e.file_name().to_string_lossy().starts_with('.')
+                            })
+                            .count()
+                    });
+                }
+            }
         }
+    }
 
-        self.list = result;
-        Ok(())
+    /// Sort the items by the sort key, with directories first if `dirs_first` is set.
+    fn sort_items(&self, items: &mut [ItemInfo]) {
+        let key = self.layout.sort_by;
+        let reverse = self.layout.sort_reverse;
+        let dirs_first = self.layout.dirs_first;
+        items.sort_by(|a, b| {
+            let group = |item: &ItemInfo| dirs_first && item.file_type != FileType::Directory;
+            let ordering = compare_items(a, b, key);
+            group(a).cmp(&group(b)).then(if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            })
+        });
     }
 
     /// Change (only) the order of the list and print it.
@@ -950,31 +1744,8 @@ impl State {
 
     /// Change the order of the list not reading all the items.
     fn change_order(&mut self) {
-        let mut dir_v = Vec::new();
-        let mut file_v = Vec::new();
-        let mut result = Vec::with_capacity(self.list.len());
-
-        for item in self.list.iter_mut() {
-            if item.file_type == FileType::Directory {
-                dir_v.push(std::mem::take(item));
-            } else {
-                file_v.push(std::mem::take(item));
-            }
-        }
-
-        match self.layout.sort_by {
-            SortKey::Name => {
-                dir_v.sort_by(|a, b| natord::compare_ignore_case(&a.file_name, &b.file_name));
-                file_v.sort_by(|a, b| natord::compare_ignore_case(&a.file_name, &b.file_name));
-            }
-            SortKey::Time => {
-                dir_v.sort_by(|a, b| b.modified.partial_cmp(&a.modified).unwrap());
-                file_v.sort_by(|a, b| b.modified.partial_cmp(&a.modified).unwrap());
-            }
-        }
-
-        result.append(&mut dir_v);
-        result.append(&mut file_v);
+        let mut result = std::mem::take(&mut self.list);
+        self.sort_items(&mut result);
 
         if !self.layout.show_hidden {
             result.retain(|x| !x.is_hidden);
@@ -983,6 +1754,25 @@ impl State {
         self.list = result;
     }
 
+    /// Items for the placeholders of the shell mode: the selected items, or the current item.
+    pub fn shell_targets(&self) -> Targets {
+        let current = self.get_item().ok().map(|item| item.file_path.clone());
+        let mut selected: Vec<PathBuf> = self
+            .list
+            .iter()
+            .filter(|item| item.selected)
+            .map(|item| item.file_path.clone())
+            .collect();
+        if selected.is_empty() {
+            selected.extend(current.clone());
+        }
+        Targets {
+            selected,
+            current,
+            dir: self.current_dir.clone(),
+        }
+    }
+
     /// Reset all item's selected state and exit the select mode.
     pub fn reset_selection(&mut self) {
         for mut item in self.list.iter_mut() {
@@ -990,9 +1780,9 @@ impl State {
         }
     }
 
-    pub fn highlight_matches(&mut self, keyword: &str) {
+    pub fn highlight_matches(&mut self, keyword: &Matcher) {
         for item in self.list.iter_mut() {
-            if item.file_name.contains(keyword) {
+            if keyword.is_match(&item.file_name) {
                 item.matches = true;
             } else {
                 item.matches = false;
@@ -1028,7 +1818,7 @@ impl State {
         move_to(1, 1);
         screen.flush()?;
         let (width, height) = terminal_size()?;
-        let help = format_txt(HELP, width, true);
+        let help = format_txt(&help_text(&self.keymap), width, true);
         print_help(&help, 0, height);
         screen.flush()?;
 
@@ -1074,19 +1864,16 @@ impl State {
                     print_info("EMPTY: Processing...", self.layout.y);
                     screen.flush()?;
 
-                    //Delete trash dir.
-                    if let Err(e) = std::fs::remove_dir_all(&self.trash_dir) {
-                        print_warning(e, self.layout.y);
-                    }
-                    //Recreate the dir.
-                    if let Err(e) = std::fs::create_dir(&self.trash_dir) {
-                        print_warning(e, self.layout.y);
-                    }
+                    //Delete and recreate trash dir.
+                    let result = trash::empty_trash(&self.trash_dir, self.xdg_trash);
                     if self.current_dir == self.trash_dir {
                         self.reload(BEGINNING_ROW)?;
                     }
                     go_to_and_rest_info();
-                    print_info("Trash dir emptied", self.layout.y);
+                    match result {
+                        Ok(_) => print_info("Trash dir emptied", self.layout.y),
+                        Err(e) => print_warning(e, self.layout.y),
+                    }
                     self.move_cursor(self.layout.y);
                     screen.flush()?;
                 }
@@ -1099,8 +1886,13 @@ impl State {
         Ok(())
     }
 
+    /// Change the directory, clearing the filter and applying the view saved for the directory.
+    /// Every move to another directory goes through here.
     pub fn chdir(&mut self, p: &std::path::Path, mv: Move) -> Result<(), FxError> {
         std::env::set_current_dir(p)?;
+        self.filter = None;
+        let preview = self.layout.preview;
+        self.apply_dir_view(p);
         match mv {
             Move::Up => {
                 // Push current state to c_memo
@@ -1193,9 +1985,268 @@ impl State {
                 self.reload(BEGINNING_ROW)?;
             }
         }
+        if self.layout.preview != preview {
+            self.refresh_preview()?;
+        }
+        Ok(())
+    }
+
+    /// Apply the view saved for the directory, or restore the global one when leaving such a directory.
+    pub fn apply_dir_view(&mut self, p: &Path) {
+        let key = fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        match self.dir_views.get(&key) {
+            Some(view) => {
+                if self.global_view.is_none() {
+                    self.global_view = Some(self.layout.view());
+                }
+                self.layout.set_view(view);
+            }
+            None => {
+                if let Some(view) = self.global_view.take() {
+                    self.layout.set_view(&view);
+                }
+            }
+        }
+        //The preview is not available in the dual-pane mode.
+        if self.pane.is_some() {
+            self.layout.preview = false;
+        }
+    }
+
+    /// Save the current view for the current directory.
+    pub fn save_dir_view(&mut self) {
+        let key = fs::canonicalize(&self.current_dir).unwrap_or_else(|_| self.current_dir.clone());
+        if self.global_view.is_none() {
+            self.global_view = Some(self.layout.view());
+        }
+        self.dir_views.insert(key, self.layout.view());
+    }
+
+    /// Remove the view saved for the current directory, and restore the global one.
+    /// Returns false if no view is saved.
+    pub fn remove_dir_view(&mut self) -> Result<bool, FxError> {
+        let key = fs::canonicalize(&self.current_dir).unwrap_or_else(|_| self.current_dir.clone());
+        if self.dir_views.remove(&key).is_none() {
+            return Ok(false);
+        }
+        let preview = self.layout.preview;
+        if let Some(view) = self.global_view.take() {
+            self.layout.set_view(&view);
+        }
+        self.layout.nums.reset();
+        self.reload(BEGINNING_ROW)?;
+        if self.layout.preview != preview {
+            self.refresh_preview()?;
+        }
+        Ok(true)
+    }
+
+    /// Go to the directory of the item, with the cursor on it.
+    pub fn jump_to(&mut self, p: &std::path::Path) -> Result<(), FxError> {
+        let (dir, name) = match (p.parent(), p.file_name()) {
+            (Some(dir), Some(name)) => (dir, name),
+            _ => return self.chdir(p, Move::Jump),
+        };
+        self.chdir(dir, Move::Jump)?;
+        if let Some(i) = self.list.iter().position(|x| {
+            let file_name = x.file_name.as_ref() as &OsStr;
+            file_name == name
+        }) {
+            if i < 3 {
+                self.layout.nums.skip = 0;
+                self.layout.nums.index = i;
+                self.redraw((i as u16) + BEGINNING_ROW);
+            } else {
+                self.layout.nums.skip = (i - 3) as u16;
+                self.layout.nums.index = i;
+                self.redraw(BEGINNING_ROW + 3);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a new tab in the current directory.
+    pub fn new_tab(&mut self) -> Result<(), FxError> {
+        self.store_tab();
+        let tab = Tab {
+            current_dir: self.current_dir.clone(),
+            nums: self.layout.nums,
+            y: self.layout.y,
+            ..Default::default()
+        };
+        self.tabs.insert(self.tab_index + 1, tab);
+        self.restore_tab(self.tab_index + 1)
+    }
+
+    /// Close the current tab and show the next one.
+    pub fn close_tab(&mut self) -> Result<(), FxError> {
+        self.tabs.remove(self.tab_index);
+        let next = if self.tab_index >= self.tabs.len() {
+            self.tabs.len() - 1
+        } else {
+            self.tab_index
+        };
+        self.restore_tab(next)
+    }
+
+    /// Switch to the next tab.
+    pub fn next_tab(&mut self) -> Result<(), FxError> {
+        self.store_tab();
+        self.restore_tab((self.tab_index + 1) % self.tabs.len())
+    }
+
+    /// Switch to the previous tab.
+    pub fn previous_tab(&mut self) -> Result<(), FxError> {
+        self.store_tab();
+        let len = self.tabs.len();
+        self.restore_tab((self.tab_index + len - 1) % len)
+    }
+
+    /// Store the state of the current tab.
+    fn store_tab(&mut self) {
+        self.tabs[self.tab_index] = self.take_tab();
+    }
+
+    /// Restore the state of the tab and redraw it.
+    fn restore_tab(&mut self, i: usize) -> Result<(), FxError> {
+        let tab = std::mem::take(&mut self.tabs[i]);
+        self.tab_index = i;
+        self.restore(tab)
+    }
+
+    /// Take the state of the current directory out, to store it in a tab or pane.
+    fn take_tab(&mut self) -> Tab {
+        Tab {
+            current_dir: self.current_dir.clone(),
+            nums: self.layout.nums,
+            y: self.layout.y,
+            c_memo: std::mem::take(&mut self.c_memo),
+            p_memo: std::mem::take(&mut self.p_memo),
+            keyword: self.keyword.take(),
+            filter: self.filter.take(),
+        }
+    }
+
+    /// Restore the state stored in a tab or pane, and redraw it.
+    fn restore(&mut self, tab: Tab) -> Result<(), FxError> {
+        //The directory may have been removed while the tab is inactive.
+        let dir = tab
+            .current_dir
+            .ancestors()
+            .find(|p| p.is_dir())
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| self.current_dir.clone());
+        let moved = dir != tab.current_dir;
+        std::env::set_current_dir(&dir)?;
+        let preview = self.layout.preview;
+        self.apply_dir_view(&dir);
+        self.current_dir = dir;
+        self.c_memo = tab.c_memo;
+        self.p_memo = tab.p_memo;
+        self.keyword = tab.keyword;
+        self.filter = tab.filter;
+        self.layout.nums = tab.nums;
+        self.update_list()?;
+        if let Some(keyword) = self.keyword.clone() {
+            self.highlight_matches(&keyword);
+        }
+
+        if moved || self.layout.nums.index >= self.list.len() {
+            self.layout.nums.reset();
+            self.redraw(BEGINNING_ROW);
+        } else {
+            self.redraw(tab.y);
+        }
+        if self.layout.preview != preview {
+            self.refresh_preview()?;
+        }
+        Ok(())
+    }
+
+    /// Toggle the dual-pane mode. The other pane starts from the current directory.
+    /// The preview is turned off in the dual-pane mode.
+    pub fn toggle_dual_pane(&mut self) -> Result<(), FxError> {
+        let (column, row) = terminal_size()?;
+        match self.pane.take() {
+            Some(_) => {
+                self.layout.set_x_offset(0);
+                self.refresh(column, row, self.layout.y)?;
+            }
+            None => {
+                self.layout.preview = false;
+                self.pane = Some(Pane {
+                    tab: Tab {
+                        current_dir: self.current_dir.clone(),
+                        nums: self.layout.nums,
+                        y: self.layout.y,
+                        ..Default::default()
+                    },
+                    list: self.read_items(&self.current_dir)?,
+                });
+                self.refresh(column / 2, row, self.layout.y)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the focus to the other pane in the dual-pane mode.
+    pub fn switch_pane(&mut self) -> Result<(), FxError> {
+        if let Some(pane) = self.pane.take() {
+            let current = Pane {
+                tab: self.take_tab(),
+                list: std::mem::take(&mut self.list),
+            };
+            self.pane = Some(current);
+            if self.layout.x_offset == 0 {
+                self.layout.set_x_offset(self.layout.terminal_column);
+            } else {
+                self.layout.set_x_offset(0);
+            }
+            self.restore(pane.tab)?;
+        }
+        Ok(())
+    }
+
+    /// Reload the item list of the inactive pane.
+    pub fn reload_pane(&mut self) -> Result<(), FxError> {
+        if let Some(pane) = &self.pane {
+            let mut list = self.read_items(&pane.tab.current_dir)?;
+            if let Some(filter) = &pane.tab.filter {
+                list.retain(|x| filter.is_match(&x.file_name));
+            }
+            if let Some(pane) = &mut self.pane {
+                if pane.tab.nums.index >= list.len() {
+                    pane.tab.nums.reset();
+                }
+                pane.list = list;
+            }
+        }
         Ok(())
     }
 
+    /// Print the inactive pane in the dual-pane mode.
+    fn print_pane(&self, pane: &Pane) {
+        let x_offset = if self.layout.x_offset == 0 {
+            self.layout.terminal_column
+        } else {
+            0
+        };
+
+        move_to(x_offset + 1, 1);
+        let dir = pane.tab.current_dir.display().to_string();
+        let dir = split_str(&dir, (self.layout.terminal_column - 1) as usize);
+        set_color(&TermColor::ForeGround(&Colorname::LightBlack));
+        print!(" {}", dir);
+        reset_color();
+
+        let skip = pane.tab.nums.skip as usize;
+        let rows = (self.layout.terminal_row - BEGINNING_ROW) as usize;
+        for (i, item) in pane.list.iter().skip(skip).take(rows).enumerate() {
+            move_to(x_offset + 3, i as u16 + BEGINNING_ROW);
+            self.print_item(item, x_offset);
+        }
+    }
+
     /// Change the cursor position, and print item information at the bottom.
     /// If preview is enabled, print text preview, contents of the directory or image preview on the right half of the terminal
     /// (To preview image, you must install chafa. See help).
@@ -1215,13 +2266,14 @@ impl State {
 
             //Print item information at the bottom
             self.print_footer(item);
+            self.print_job_status();
 
             //Print preview if preview is on
             if self.layout.preview {
                 self.layout.print_preview(item, y);
             }
         }
-        move_to(1, y);
+        move_to(self.layout.x_offset + 1, y);
         print_pointer();
         move_left(1);
 
@@ -1230,7 +2282,7 @@ impl State {
     }
 
     pub fn to_status_bar(&self) {
-        move_to(1, self.layout.terminal_row);
+        move_to(self.layout.x_offset + 1, self.layout.terminal_row);
     }
 
     pub fn clear_status_line(&self) {
@@ -1241,7 +2293,7 @@ impl State {
             "{}",
             " ".repeat(self.layout.terminal_column as usize).negative(),
         );
-        move_to(1, self.layout.terminal_row);
+        self.to_status_bar();
     }
 
     /// Print item information at the bottom of the terminal.
@@ -1252,7 +2304,7 @@ impl State {
             let count = self
                 .list
                 .iter()
-                .filter(|x| x.file_name.contains(keyword))
+                .filter(|x| keyword.is_match(&x.file_name))
                 .count();
             let count = if count <= 1 {
                 format!("{} match", count)
@@ -1263,72 +2315,54 @@ impl State {
                 "{}",
                 " ".repeat(self.layout.terminal_column as usize).negative(),
             );
-            move_to(1, self.layout.terminal_row);
+            self.to_status_bar();
             print!(
                 "{}{}{}{}",
-                " /".negative(),
-                keyword.clone().negative(),
+                format!(" {}", keyword.mode().prompt()).negative(),
+                keyword.keyword().negative(),
                 " - ".negative(),
                 count.negative()
             );
             return;
         }
 
-        let footer = self.make_footer(item);
+        let footer = match &self.filter {
+            Some(filter) => format!(
+                " [filter {}{}]{}",
+                filter.mode().prompt(),
+                filter.keyword(),
+                self.make_footer(item)
+            ),
+            None => self.make_footer(item),
+        };
+        let footer: String = footer
+            .chars()
+            .take(self.layout.terminal_column.into())
+            .collect();
         print!("{}", footer.negative());
     }
 
     fn make_footer(&self, item: &ItemInfo) -> String {
-        match &item.file_ext {
-            Some(ext) => {
-                let footer = match item.permissions {
-                    Some(permissions) => {
-                        format!(
-                            " {}/{} {} {} {}",
-                            self.layout.nums.index + 1,
-                            self.list.len(),
-                            ext.clone(),
-                            to_proper_size(item.file_size),
-                            convert_to_permissions(permissions)
-                        )
-                    }
-                    None => format!(
-                        " {}/{} {} {}",
-                        self.layout.nums.index + 1,
-                        self.list.len(),
-                        ext.clone(),
-                        to_proper_size(item.file_size),
-                    ),
-                };
-                footer
-                    .chars()
-                    .take(self.layout.terminal_column.into())
-                    .collect()
-            }
-            None => {
-                let footer = match item.permissions {
-                    Some(permissions) => {
-                        format!(
-                            " {}/{} {} {}",
-                            self.layout.nums.index + 1,
-                            self.list.len(),
-                            to_proper_size(item.file_size),
-                            convert_to_permissions(permissions)
-                        )
-                    }
-                    None => format!(
-                        " {}/{} {}",
-                        self.layout.nums.index + 1,
-                        self.list.len(),
-                        to_proper_size(item.file_size),
-                    ),
-                };
-                footer
-                    .chars()
-                    .take(self.layout.terminal_column.into())
-                    .collect()
-            }
+        let mut footer = format!(" {}/{}", self.layout.nums.index + 1, self.list.len());
+        if let Some(ext) = &item.file_ext {
+            footer.push(' ');
+            footer.push_str(ext);
+        }
+        footer.push(' ');
+        footer.push_str(&to_proper_size(item.file_size));
+        //`ls -l` style, such as `drwxr-sr-x user group`.
+        if let Some(permissions) = item.permissions {
+            footer.push(' ');
+            footer.push_str(&format_permissions(permissions));
         }
+        if let (Some(uid), Some(gid)) = (item.uid, item.gid) {
+            let (user, group) = self.owner_names.get(uid, gid);
+            footer.push_str(&format!(" {} {}", user, group));
+        }
+        footer
+            .chars()
+            .take(self.layout.terminal_column.into())
+            .collect()
     }
 
     pub fn scroll_down_preview(&mut self, y: u16) {
@@ -1350,7 +2384,7 @@ impl State {
     fn scroll_preview(&self, y: u16) {
         if let Ok(item) = self.get_item() {
             self.layout.print_preview(item, y);
-            move_to(1, y);
+            move_to(self.layout.x_offset + 1, y);
             print_pointer();
             move_left(1);
         }
@@ -1358,43 +2392,104 @@ impl State {
 
     /// Store the sort key and whether to show hidden items to session file.
     pub fn write_session(&self, session_path: PathBuf) -> Result<(), FxError> {
+        //The view saved for the current directory is not the global one.
+        let view = self.global_view.unwrap_or_else(|| self.layout.view());
         let session = Session {
-            sort_by: self.layout.sort_by.clone(),
-            show_hidden: self.layout.show_hidden,
-            preview: Some(self.layout.preview),
+            sort_by: view.sort_by,
+            show_hidden: view.show_hidden,
+            preview: Some(view.preview),
             split: Some(self.layout.split),
+            sort_reverse: Some(view.sort_reverse),
+            dirs_first: Some(self.layout.dirs_first),
+            dirs: Some(self.dir_views.clone()),
         };
         let serialized = serde_yaml::to_string(&session)?;
         fs::write(session_path, serialized)?;
         Ok(())
     }
 
-    /// Unpack/unarchive a file.
-    pub fn unpack(&self) -> Result<(), FxError> {
+    /// Unpack/unarchive a file in a job.
+    pub fn unpack(&mut self) -> Result<(), FxError> {
         let item = self.get_item()?;
         let p = item.file_path.clone();
 
+        //The list may not contain hidden or filtered-out items.
         let mut name_set: BTreeSet<String> = BTreeSet::new();
-        for item in self.list.iter() {
-            name_set.insert(item.file_name.clone());
+        for entry in fs::read_dir(&self.current_dir)? {
+            let entry = entry?;
+            name_set.insert(
+                entry
+                    .file_name()
+                    .into_string()
+                    .unwrap_or_else(|_| "".to_string()),
+            );
         }
 
         let dest_name = rename_dir(&item.file_name, &name_set);
         let mut dest = self.current_dir.clone();
         dest.push(dest_name);
+        //Reserve the destination, so that another job does not unpack into it.
+        fs::create_dir(&dest)?;
 
-        magic_packed::unpack(&p, &dest)?;
+        self.jobs
+            .spawn(JobKind::Unpack { archive: p, dest }, Vec::new());
         Ok(())
     }
 }
 
+/// Remove the `.trashinfo` files written for the backups of the tasks not run,
+/// as they also reserve the names in the XDG trash.
+fn discard_backups(tasks: &[Task]) {
+    for task in tasks {
+        if let Some(backup) = &task.backup {
+            trash::discard_info(backup);
+        }
+    }
+}
+
+/// Create a file in a new directory only accessible by the user, failing if either already exists,
+/// so that other users cannot plant a symlink or replace the content.
+/// Returns the paths of the directory and the file.
+fn create_private_temp_file(prefix: &str, content: &str) -> Result<(PathBuf, PathBuf), FxError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let dir = env::temp_dir().join(format!("{}_{}_{}", prefix, std::process::id(), nanos));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+
+    let path = dir.join("names");
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e.into());
+    }
+    Ok((dir, path))
+}
+
 /// Read item information from `std::fs::DirEntry`.
 fn read_item(entry: fs::DirEntry) -> ItemInfo {
-    let path = entry.path();
+    read_path(entry.path())
+}
+
+/// Read item information from the path.
+pub fn read_path(path: PathBuf) -> ItemInfo {
     let metadata = fs::symlink_metadata(&path);
 
-    let name = entry
+    let name = path
         .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default()
         .into_string()
         .unwrap_or_else(|_| "Invalid unicode name".to_string());
 
@@ -1448,6 +2543,16 @@ fn read_item(entry: fs::DirEntry) -> ItemInfo {
             let permissions = Some(metadata.permissions().mode());
             #[cfg(not(target_family = "unix"))]
             let permissions = None;
+            #[cfg(target_family = "unix")]
+            let (uid, gid) = (Some(metadata.uid()), Some(metadata.gid()));
+            #[cfg(not(target_family = "unix"))]
+            let (uid, gid) = (None, None);
+
+            let link_target = if filetype == FileType::Symlink {
+                fs::read_link(&path).ok()
+            } else {
+                None
+            };
 
             let size = metadata.len();
             ItemInfo {
@@ -1461,6 +2566,8 @@ fn read_item(entry: fs::DirEntry) -> ItemInfo {
                     _ => ext,
                 },
                 modified: time,
+                created: metadata.created().ok(),
+                accessed: metadata.accessed().ok(),
                 selected: false,
                 matches: false,
                 is_hidden: hidden,
@@ -1468,6 +2575,11 @@ fn read_item(entry: fs::DirEntry) -> ItemInfo {
                 preview_scroll: 0,
                 content: None,
                 permissions,
+                uid,
+                gid,
+                owner: None,
+                link_target,
+                dir_count: None,
             }
         }
         Err(_) => ItemInfo {
@@ -1478,6 +2590,8 @@ fn read_item(entry: fs::DirEntry) -> ItemInfo {
             file_size: 0,
             file_ext: ext,
             modified: None,
+            created: None,
+            accessed: None,
             selected: false,
             matches: false,
             is_hidden: false,
@@ -1485,26 +2599,70 @@ fn read_item(entry: fs::DirEntry) -> ItemInfo {
             preview_scroll: 0,
             content: None,
             permissions: None,
+            uid: None,
+            gid: None,
+            owner: None,
+            link_target: None,
+            dir_count: None,
         },
     }
 }
 
-/// Generate item information from trash directory, in order to use when redoing.
-pub fn trash_to_info(trash_dir: &PathBuf, vec: &[PathBuf]) -> Result<Vec<ItemInfo>, FxError> {
-    let total = vec.len();
-    let mut count = 0;
-    let mut result = Vec::new();
-    for entry in fs::read_dir(trash_dir)? {
-        let entry = entry?;
-        if vec.contains(&entry.path()) {
-            result.push(read_item(entry));
-            count += 1;
-            if count == total {
-                break;
-            }
-        }
+/// Value of the column in the item list.
+fn column_value(item: &ItemInfo, column: Column) -> String {
+    match column {
+        Column::Name => item.file_name.clone(),
+        Column::Size => match item.file_type {
+            FileType::Directory => "".to_owned(),
+            _ => to_proper_size(item.file_size),
+        },
+        Column::Mtime => format_time(&item.modified),
+        Column::Permissions => item.permissions.map(format_permissions).unwrap_or_default(),
+        Column::Owner => item.owner.clone().unwrap_or_default(),
+        Column::LinkTarget => item
+            .link_target
+            .as_ref()
+            .map(|target| format!("-> {}", target.display()))
+            .unwrap_or_default(),
+        Column::Count => item.dir_count.map(|n| n.to_string()).unwrap_or_default(),
+    }
+}
+
+/// Truncate or pad the value to the display width.
+fn fit_to_width(value: &str, width: usize, align_right: bool) -> String {
+    let value = truncate_to_width(value, width);
+    let padding = " ".repeat(width.saturating_sub(value.width()));
+    if align_right {
+        format!("{}{}", padding, value)
+    } else {
+        format!("{}{}", value, padding)
     }
-    Ok(result)
+}
+
+/// Compare two items by the sort key, then by the name.
+/// Times and sizes are in descending order, others in ascending order.
+fn compare_items(a: &ItemInfo, b: &ItemInfo, key: SortKey) -> Ordering {
+    let ordering = match key {
+        SortKey::Name => Ordering::Equal,
+        SortKey::Time => b.modified.cmp(&a.modified),
+        SortKey::Size => b.file_size.cmp(&a.file_size),
+        SortKey::Extension => a.file_ext.cmp(&b.file_ext),
+        SortKey::Created => b.created.cmp(&a.created),
+        SortKey::Accessed => b.accessed.cmp(&a.accessed),
+        SortKey::Type => a
+            .file_type
+            .cmp(&b.file_type)
+            .then(a.file_ext.cmp(&b.file_ext)),
+    };
+    ordering.then_with(|| natord::compare_ignore_case(&a.file_name, &b.file_name))
+}
+
+/// Generate item information of items in the trash, in order to use when undoing.
+pub fn trash_to_info(vec: &[PathBuf]) -> Vec<ItemInfo> {
+    vec.iter()
+        .filter(|p| fs::symlink_metadata(p).is_ok())
+        .map(|p| read_path(p.to_path_buf()))
+        .collect()
 }
 
 fn check_chafa() -> bool {
@@ -1632,6 +2790,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sort_items() {
+        let item = |name: &str, file_type: FileType, size: u64| ItemInfo {
+            file_type,
+            file_name: name.to_owned(),
+            file_ext: Path::new(name)
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string()),
+            file_size: size,
+            ..Default::default()
+        };
+        let mut items = vec![
+            item("b.txt", FileType::File, 10),
+            item("src", FileType::Directory, 4096),
+            item("a10.rs", FileType::File, 30),
+            item("a2.rs", FileType::File, 30),
+            item("link", FileType::Symlink, 5),
+        ];
+        let names = |items: &[ItemInfo]| -> Vec<String> {
+            items.iter().map(|item| item.file_name.clone()).collect()
+        };
+
+        items.sort_by(|a, b| compare_items(a, b, SortKey::Name));
+        assert_eq!(
+            names(&items),
+            vec!["a2.rs", "a10.rs", "b.txt", "link", "src"]
+        );
+        items.sort_by(|a, b| compare_items(a, b, SortKey::Size));
+        assert_eq!(
+            names(&items),
+            vec!["src", "a2.rs", "a10.rs", "b.txt", "link"]
+        );
+        items.sort_by(|a, b| compare_items(a, b, SortKey::Extension));
+        assert_eq!(
+            names(&items),
+            vec!["link", "src", "a2.rs", "a10.rs", "b.txt"]
+        );
+        items.sort_by(|a, b| compare_items(a, b, SortKey::Type));
+        assert_eq!(
+            names(&items),
+            vec!["src", "a2.rs", "a10.rs", "b.txt", "link"]
+        );
+    }
+
     #[test]
     fn bench_update_single() {
         let bench_result = run_benchmark(100, |_| {
@@ -0,0 +1,34 @@
+use super::matcher::Matcher;
+use super::nums::{Num, StateMemo};
+use super::state::ItemInfo;
+
+use std::path::{Path, PathBuf};
+
+/// State which each tab keeps on its own.
+/// The registered items and operations are shared between tabs.
+#[derive(Debug, Default)]
+pub struct Tab {
+    pub current_dir: PathBuf,
+    pub nums: Num,
+    pub y: u16,
+    pub c_memo: Vec<StateMemo>,
+    pub p_memo: Vec<StateMemo>,
+    pub keyword: Option<Matcher>,
+    pub filter: Option<Matcher>,
+}
+
+/// The inactive pane in the dual-pane mode.
+#[derive(Debug)]
+pub struct Pane {
+    pub tab: Tab,
+    pub list: Vec<ItemInfo>,
+}
+
+/// Label of the tab in the headline: index (1-based) and the directory name.
+pub fn tab_label(i: usize, dir: &Path) -> String {
+    let name = match dir.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => dir.display().to_string(),
+    };
+    format!(" {}:{} ", i + 1, name)
+}
@@ -4,6 +4,10 @@ use super::errors::FxError;
 use crossterm::cursor::{Hide, MoveLeft, MoveRight, MoveTo, Show};
 use crossterm::style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor};
 use crossterm::terminal::Clear;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+/// Column where the cursor pointer is printed, which changes in the dual-pane mode.
+static POINTER_COLUMN: AtomicU16 = AtomicU16::new(1);
 
 pub enum TermColor<'a> {
     ForeGround(&'a Colorname),
@@ -73,6 +77,15 @@ pub fn show_cursor() {
     print!("{}", Show);
 }
 
+pub fn set_pointer_column(x: u16) {
+    POINTER_COLUMN.store(x, Ordering::Relaxed);
+}
+
+/// Move to the pointer column of the row.
+pub fn move_to_pointer(y: u16) {
+    move_to(POINTER_COLUMN.load(Ordering::Relaxed), y);
+}
+
 pub fn print_pointer() {
     print!(">");
 }
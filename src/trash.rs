@@ -0,0 +1,519 @@
+use super::errors::FxError;
+use super::functions::{move_item, rename_dir, rename_file};
+use super::job::remove_item;
+
+use chrono::prelude::*;
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Length of the time prefix of items in felix's own trash directory (`timestamp_name`).
+pub const TIME_PREFIX: usize = 11;
+const TRASH_INFO_HEADER: &str = "[Trash Info]";
+/// Directory of `.trashinfo` files in felix's own trash directory.
+const FELIX_INFO_DIR: &str = ".trashinfo";
+
+/// Item in the trash, with the metadata to restore it.
+#[derive(Debug, Clone)]
+pub struct TrashItem {
+    pub path: PathBuf,
+    pub name: String,
+    pub original_dir: Option<PathBuf>,
+    pub deleted: Option<String>,
+}
+
+/// Make `files/` and `info/` of the XDG home trash (`$XDG_DATA_HOME/Trash`), and return `files/`.
+pub fn init_xdg_home_trash() -> Result<PathBuf, FxError> {
+    let home_trash = dirs::data_dir()
+        .ok_or_else(|| FxError::Dirs("Cannot read the data local directory.".to_string()))?
+        .join("Trash");
+    let files = home_trash.join("files");
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(home_trash.join("info"))?;
+    Ok(files)
+}
+
+/// Make the path in the trash to move the item to, and write the `.trashinfo` file.
+/// `trash_dir` is the trash directory in use: felix's own one, or `files/` of the XDG home trash.
+/// The `.trashinfo` file also reserves the name, so that items deleted at the same time do not collide.
+pub fn to_trash_path(
+    item: &Path,
+    name: &str,
+    trash_dir: &Path,
+    xdg: bool,
+) -> Result<PathBuf, FxError> {
+    if !xdg {
+        let timestamp = Local::now().timestamp();
+        let info = trash_dir.join(FELIX_INFO_DIR);
+        std::fs::create_dir_all(&info)?;
+        let content = trash_info(item);
+
+        let mut taken = BTreeSet::new();
+        loop {
+            let candidate = rename_file(name, &taken);
+            let trash_name = format!("{}_{}", timestamp, candidate);
+            if std::fs::symlink_metadata(trash_dir.join(&trash_name)).is_err() {
+                match OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(info.join(format!("{}.trashinfo", trash_name)))
+                {
+                    Ok(mut file) => {
+                        file.write_all(content.as_bytes())?;
+                        return Ok(trash_dir.join(trash_name));
+                    }
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            taken.insert(candidate);
+        }
+    }
+
+    let home_trash = trash_dir.parent().unwrap_or(trash_dir);
+    match mount_trash(item, home_trash) {
+        Some((trash, topdir)) => write_trash_info(&trash, item, name, Some(&topdir)),
+        None => write_trash_info(home_trash, item, name, None),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+/// For items on another device than the home trash, use `$topdir/.Trash-$UID` of the mount point.
+/// Returns the trash and the top directory, or None if the home trash should be used.
+fn mount_trash(item: &Path, home_trash: &Path) -> Option<(PathBuf, PathBuf)> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = std::fs::symlink_metadata(item).ok()?.dev();
+    if std::fs::metadata(home_trash).ok()?.dev() == dev {
+        return None;
+    }
+
+    //The top directory is the last ancestor on the same device.
+    let mut topdir = item.parent()?;
+    while let Some(parent) = topdir.parent() {
+        match std::fs::metadata(parent) {
+            Ok(metadata) if metadata.dev() == dev => topdir = parent,
+            _ => break,
+        }
+    }
+
+    let trash = topdir.join(format!(".Trash-{}", nix::unistd::getuid()));
+    if std::fs::create_dir_all(trash.join("files")).is_err()
+        || std::fs::create_dir_all(trash.join("info")).is_err()
+    {
+        return None;
+    }
+    Some((trash, topdir.to_path_buf()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+fn mount_trash(_item: &Path, _home_trash: &Path) -> Option<(PathBuf, PathBuf)> {
+    None
+}
+
+/// Trash directories to list and empty: the one in use, and `files/` of `.Trash-$UID` of the mount points in the XDG layout.
+pub fn trash_dirs(trash_dir: &Path, xdg: bool) -> Vec<PathBuf> {
+    let mut dirs = vec![trash_dir.to_path_buf()];
+    if xdg {
+        for dir in mount_trash_dirs() {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+/// `files/` of `.Trash-$UID` of the mounted filesystems, read from `/proc/self/mounts`.
+fn mount_trash_dirs() -> Vec<PathBuf> {
+    let trash_name = format!(".Trash-{}", nix::unistd::getuid());
+    let mounts = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|mount_point| {
+            PathBuf::from(decode_mount_point(mount_point))
+                .join(&trash_name)
+                .join("files")
+        })
+        .filter(|files| files.is_dir())
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "netbsd")))]
+fn mount_trash_dirs() -> Vec<PathBuf> {
+    vec![]
+}
+
+/// Decode the octal escapes such as `\040` (space) in the mount point.
+#[cfg(any(target_os = "linux", target_os = "netbsd"))]
+fn decode_mount_point(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(octal, 8) {
+                result.push(b);
+                i += 4;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+/// Write `info/<name>.trashinfo` with a name unused in `files/` and `info/`.
+/// In the trash of a mount point, the original path is relative to the top directory.
+fn write_trash_info(
+    trash: &Path,
+    item: &Path,
+    name: &str,
+    topdir: Option<&Path>,
+) -> Result<PathBuf, FxError> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    let path = match topdir {
+        Some(topdir) => item.strip_prefix(topdir).unwrap_or(item),
+        None => item,
+    };
+    let content = trash_info(path);
+
+    let mut taken = BTreeSet::new();
+    loop {
+        let candidate = rename_file(name, &taken);
+        if std::fs::symlink_metadata(files.join(&candidate)).is_err() {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(info.join(format!("{}.trashinfo", candidate)))
+            {
+                Ok(mut file) => {
+                    file.write_all(content.as_bytes())?;
+                    return Ok(files.join(candidate));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        taken.insert(candidate);
+    }
+}
+
+fn trash_info(path: &Path) -> String {
+    format!(
+        "{}\nPath={}\nDeletionDate={}\n",
+        TRASH_INFO_HEADER,
+        encode_path(path),
+        Local::now().format("%Y-%m-%dT%H:%M:%S")
+    )
+}
+
+/// The `.trashinfo` file of the item: `info/` of an XDG trash, or `.trashinfo/` of felix's own trash.
+fn info_path(p: &Path) -> Option<PathBuf> {
+    let dir = p.parent()?;
+    let mut info_name = p.file_name()?.to_os_string();
+    info_name.push(".trashinfo");
+    let info = if dir.file_name()? == "files" {
+        dir.parent()?.join("info").join(info_name)
+    } else {
+        dir.join(FELIX_INFO_DIR).join(info_name)
+    };
+    if info.exists() {
+        Some(info)
+    } else {
+        None
+    }
+}
+
+/// Original path recorded in the `.trashinfo` file.
+/// In the trash of a mount point, the relative path is joined to the top directory.
+pub fn original_path(p: &Path) -> Option<PathBuf> {
+    let path = PathBuf::from(decode_path(&read_info(p, "Path=")?));
+    if path.is_absolute() {
+        return Some(path);
+    }
+    //`$topdir/.Trash-$UID/files/<item>`
+    let topdir = p.parent()?.parent()?.parent()?;
+    Some(topdir.join(path))
+}
+
+fn read_info(p: &Path, key: &str) -> Option<String> {
+    let info = std::fs::read_to_string(info_path(p)?).ok()?;
+    info.lines()
+        .find_map(|line| line.strip_prefix(key))
+        .map(|value| value.to_owned())
+}
+
+/// List items in the trash directories, the latest deleted first.
+pub fn list_trash(trash_dir: &Path, xdg: bool) -> Result<Vec<TrashItem>, FxError> {
+    let mut items = Vec::new();
+    for (i, dir) in trash_dirs(trash_dir, xdg).iter().enumerate() {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            //Trashes of the mount points may be unreadable.
+            Err(_) if i > 0 => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.file_name() == Some(std::ffi::OsStr::new(FELIX_INFO_DIR)) {
+                continue;
+            }
+            let original_dir = original_path(&path)
+                .and_then(|original| original.parent().map(|dir| dir.to_path_buf()));
+            let deleted = read_info(&path, "DeletionDate=")
+                .map(|date| date.replace('T', " ").chars().take(16).collect())
+                .or_else(|| {
+                    if xdg {
+                        None
+                    } else {
+                        deleted_from_prefix(&path)
+                    }
+                });
+            items.push(TrashItem {
+                name: original_name(&path, trash_dir, xdg),
+                path,
+                original_dir,
+                deleted,
+            });
+        }
+    }
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+    Ok(items)
+}
+
+/// Deletion time of items without `.trashinfo`, from the time prefix.
+fn deleted_from_prefix(p: &Path) -> Option<String> {
+    let name = p.file_name()?.to_str()?;
+    let timestamp: i64 = name.get(..TIME_PREFIX - 1)?.parse().ok()?;
+    let time = Local.timestamp_opt(timestamp, 0).single()?;
+    Some(time.format("%Y-%m-%d %H:%M").to_string())
+}
+
+/// Move the item in the trash to the directory, renaming it on the name conflict.
+pub fn restore(item: &TrashItem, dir: &Path) -> Result<PathBuf, FxError> {
+    std::fs::create_dir_all(dir)?;
+    let mut name_set = BTreeSet::new();
+    for entry in std::fs::read_dir(dir)? {
+        name_set.insert(entry?.file_name().to_string_lossy().to_string());
+    }
+    let name = if item.path.is_dir() {
+        rename_dir(&item.name, &name_set)
+    } else {
+        rename_file(&item.name, &name_set)
+    };
+    let dest = dir.join(name);
+    move_item(&item.path, &dest)?;
+    discard_info(&item.path);
+    Ok(dest)
+}
+
+/// Delete the item in the trash permanently.
+pub fn delete_permanently(item: &TrashItem) -> Result<(), FxError> {
+    if remove_item(&item.path).is_err() {
+        return Err(FxError::RemoveItem(item.path.clone()));
+    }
+    discard_info(&item.path);
+    Ok(())
+}
+
+/// Name of the item before deleted, without the time prefix or the suffix added in the trash.
+/// The time prefix is only in felix's own trash directory.
+pub fn original_name(p: &Path, trash_dir: &Path, xdg: bool) -> String {
+    let name = p
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if let Some(original) = original_path(p) {
+        if let Some(original_name) = original.file_name() {
+            return original_name.to_string_lossy().to_string();
+        }
+    }
+    if !xdg && p.parent() == Some(trash_dir) && name.len() > TIME_PREFIX {
+        return name.chars().skip(TIME_PREFIX).collect();
+    }
+    name
+}
+
+/// Remove the `.trashinfo` file when the item is not (or no longer) in the trash.
+pub fn discard_info(p: &Path) {
+    if let Some(info) = info_path(p) {
+        let _ = std::fs::remove_file(info);
+    }
+}
+
+/// Empty the trash directories, along with `info/` in the XDG layout.
+/// Directories that cannot be emptied, such as on a read-only mount, are skipped and reported at the end.
+pub fn empty_trash(trash_dir: &Path, xdg: bool) -> Result<(), FxError> {
+    let mut dirs = Vec::new();
+    for files in trash_dirs(trash_dir, xdg) {
+        if xdg {
+            if let Some(trash) = files.parent() {
+                dirs.push(trash.join("info"));
+            }
+        }
+        dirs.push(files);
+    }
+    let mut failed = Vec::new();
+    for dir in dirs {
+        let result = match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => std::fs::create_dir_all(&dir),
+        };
+        if result.is_err() {
+            failed.push(dir.display().to_string());
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(FxError::Io(format!("Cannot empty {}", failed.join(", "))))
+    }
+}
+
+/// Percent-encode the path for `.trashinfo`, as the specification requires.
+fn encode_path(p: &Path) -> String {
+    let mut result = String::new();
+    for b in p.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            result.push(b as char);
+        } else {
+            result.push_str(&format!("%{:02X}", b));
+        }
+    }
+    result
+}
+
+fn decode_path(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                result.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_path() {
+        let p = Path::new("/home/user/a b/ファイル%.txt");
+        let encoded = encode_path(p);
+        assert_eq!(
+            encoded,
+            "/home/user/a%20b/%E3%83%95%E3%82%A1%E3%82%A4%E3%83%AB%25.txt"
+        );
+        assert_eq!(decode_path(&encoded), p.to_string_lossy());
+        assert_eq!(decode_path("100%"), "100%");
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "netbsd"))]
+    #[test]
+    fn test_decode_mount_point() {
+        assert_eq!(decode_mount_point("/mnt/my\\040disk"), "/mnt/my disk");
+        assert_eq!(decode_mount_point("/"), "/");
+    }
+
+    #[test]
+    fn test_trash_info() {
+        let base = std::env::temp_dir().join("felix_test_trash_info");
+        let _ = std::fs::remove_dir_all(&base);
+        let home_trash = base.join("Trash");
+        std::fs::create_dir_all(home_trash.join("files")).unwrap();
+        std::fs::create_dir_all(home_trash.join("info")).unwrap();
+        let item = base.join("a.txt");
+        std::fs::write(&item, "a").unwrap();
+
+        let first = to_trash_path(&item, "a.txt", &home_trash.join("files"), true).unwrap();
+        let second = to_trash_path(&item, "a.txt", &home_trash.join("files"), true).unwrap();
+        assert_eq!(first, home_trash.join("files/a.txt"));
+        assert_eq!(second, home_trash.join("files/a_1.txt"));
+        assert!(home_trash.join("info/a_1.txt.trashinfo").exists());
+        assert_eq!(original_path(&second), Some(item.clone()));
+        assert_eq!(
+            original_name(&second, &home_trash.join("files"), true),
+            "a.txt"
+        );
+
+        //Items without `.trashinfo` keep the name in the XDG layout.
+        let unknown = home_trash.join("files/1671234567_b.txt");
+        std::fs::write(&unknown, "b").unwrap();
+        assert_eq!(
+            original_name(&unknown, &home_trash.join("files"), true),
+            "1671234567_b.txt"
+        );
+        assert_eq!(
+            original_name(&unknown, &home_trash.join("files"), false),
+            "b.txt"
+        );
+
+        //The relative path in the trash of a mount point is joined to the top directory.
+        let mount_trash = base.join("mnt/.Trash-1000");
+        std::fs::create_dir_all(mount_trash.join("files")).unwrap();
+        std::fs::create_dir_all(mount_trash.join("info")).unwrap();
+        std::fs::write(mount_trash.join("files/c.txt"), "c").unwrap();
+        std::fs::write(
+            mount_trash.join("info/c.txt.trashinfo"),
+            "[Trash Info]\nPath=dir/c.txt\nDeletionDate=2022-12-17T00:00:00\n",
+        )
+        .unwrap();
+        assert_eq!(
+            original_path(&mount_trash.join("files/c.txt")),
+            Some(base.join("mnt/dir/c.txt"))
+        );
+
+        discard_info(&second);
+        assert!(!home_trash.join("info/a_1.txt.trashinfo").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_restore() {
+        let base = std::env::temp_dir().join("felix_test_list_and_restore");
+        let _ = std::fs::remove_dir_all(&base);
+        let trash_dir = base.join("Trash");
+        let original = base.join("dir");
+        std::fs::create_dir_all(&trash_dir).unwrap();
+        std::fs::create_dir_all(&original).unwrap();
+        let item = original.join("a.txt");
+        std::fs::write(&item, "a").unwrap();
+
+        let trashed = to_trash_path(&item, "a.txt", &trash_dir, false).unwrap();
+        //The name reserved by the `.trashinfo` file is not used again.
+        let second = to_trash_path(&item, "a.txt", &trash_dir, false).unwrap();
+        assert_ne!(trashed, second);
+        discard_info(&second);
+        std::fs::rename(&item, &trashed).unwrap();
+        let items = list_trash(&trash_dir, false).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "a.txt");
+        assert_eq!(items[0].original_dir, Some(original.clone()));
+        assert!(items[0].deleted.is_some());
+
+        //Restore with renaming on the name conflict.
+        std::fs::write(&item, "b").unwrap();
+        let restored = restore(&items[0], &original).unwrap();
+        assert_eq!(restored, original.join("a_1.txt"));
+        assert!(list_trash(&trash_dir, false).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}